@@ -1,21 +1,21 @@
 echo "/////////////////////////////////////////////////////////////////////////////////"
-cargo run --release -- --add discontinuous.txt --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --get
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --delete
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 add discontinuous.txt
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 process
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 get
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 delete
 echo "/////////////////////////////////////////////////////////////////////////////////"
-cargo run --release -- --add discontinuous_2.txt --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --get
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --delete
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 add discontinuous_2.txt
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 process
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 get
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 delete
 echo "/////////////////////////////////////////////////////////////////////////////////"
-cargo run --release -- --add discontinuous_3.txt --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --get
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --delete
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 add discontinuous_3.txt
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 process
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 get
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 delete
 echo "/////////////////////////////////////////////////////////////////////////////////"
-cargo run --release -- --add discontinuous_4.txt --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --get
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000 --delete
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 add discontinuous_4.txt
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 process
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 get
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 delete
 echo "/////////////////////////////////////////////////////////////////////////////////"
\ No newline at end of file
@@ -1 +1 @@
-cargo run -- --add example.txt --location parachute-database --endpoint https://nyc3.digitaloceanspaces.com
+cargo run -- --location parachute-database --endpoint https://nyc3.digitaloceanspaces.com add example.txt
@@ -1 +1 @@
-cargo run --release -- --add princess.txt --location database --endpoint http://192.168.1.9:9000
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 add princess.txt
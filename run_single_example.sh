@@ -1,2 +1,2 @@
-cargo run --release -- --location database --endpoint http://192.168.1.9:9000
+cargo run --release -- --location database --endpoint http://192.168.1.9:9000 process
 
@@ -1,67 +1,87 @@
-use std::collections::HashSet;
+use std::{collections::HashMap, path::Path};
 
-use crate::line::Line;
-
-fn sentences_to_pairs(sentences: Vec<Vec<String>>) -> Vec<Line> {
-    sentences
-        .iter()
-        .filter(|sentence| sentence.len() > 1)
-        .flat_map(|sentence| split_sentence_to_pairs(sentence.clone()))
-        .collect()
-}
+use crate::error::{Error, Result};
+use itertools::Itertools;
 
-fn split_sentence_to_pairs(words: Vec<String>) -> Vec<Line> {
-    let mut shifted = words.iter();
-    shifted.next().expect("there must be something here");
-    std::iter::zip(words.iter(), shifted)
-        .map(|(f, s)| Line {
-            first: f.clone(),
-            second: s.clone(),
-        })
-        .collect()
-}
+use crate::concordance::{Concordance, Sample};
+use crate::ingestion::Ingestion;
+use crate::line::Line;
+use crate::pairing::Pairing;
+use crate::tokenizer::{Tokenization, Tokenizer};
+use crate::vocabulary::Vocabulary;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Book {
     chunk: String,
     file_name: String,
     chunk_number: usize,
+    chunker: String,
+    tokenization: Tokenization,
+    pairing: Pairing,
+    concordance: usize,
 }
 
 impl Book {
-    pub fn book_from_text(file_name: &str, chunk: &str, chunk_number: usize) -> Self {
+    pub fn book_from_text(
+        file_name: &str,
+        chunk: &str,
+        chunk_number: usize,
+        ingestion: &Ingestion,
+    ) -> Self {
         Book {
             chunk: chunk.to_owned(),
             file_name: file_name.to_owned(),
             chunk_number,
+            chunker: ingestion.chunker.to_string(),
+            tokenization: ingestion.tokenizer,
+            pairing: ingestion.pairing,
+            concordance: ingestion.concordance,
         }
     }
-    pub fn make_pairs(&self) -> HashSet<Line> {
-        HashSet::from_iter(sentences_to_pairs(split_book_to_sentences(
-            self.chunk.to_string(),
-        )))
+    pub fn chunker(&self) -> &str {
+        &self.chunker
     }
-    pub fn calculate_name(&self) -> String {
-        let (name, extension) = self.file_name.split_once('.').unwrap();
-        name.to_string() + "-" + &self.chunk_number.to_string() + "." + extension
+    pub fn tokenization(&self) -> Tokenization {
+        self.tokenization
     }
-}
-
-pub fn split_book_to_sentences(book: String) -> Vec<Vec<String>> {
-    book.split_terminator(&['.', '!', '?', ';', '\n'])
-        .filter(|x| !x.is_empty())
-        .map(|x| x.trim())
-        .map(|sentence| {
-            sentence
-                .split_ascii_whitespace()
-                .map(|s| {
-                    s.chars()
-                        .filter(|c| c.is_alphabetic())
-                        .collect::<String>()
-                        .to_lowercase()
-                })
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<_>>()
+    pub fn pairing(&self) -> Pairing {
+        self.pairing
+    }
+    // counts each pair, and keeps up to the concordance limit of the sentences behind it
+    pub fn make_pairs(&self, vocabulary: &mut Vocabulary) -> (HashMap<Line, u64>, Concordance) {
+        let mut counts = HashMap::default();
+        let mut concordance = Concordance::new(self.concordance);
+        for sentence in self.tokenization.sentences(&self.chunk) {
+            let words = self
+                .tokenization
+                .words(sentence)
+                .iter()
+                .map(|word| vocabulary.intern(word))
+                .collect::<Vec<_>>();
+            // only built when sentences are kept, so ingestion costs nothing extra otherwise
+            let sample = (self.concordance > 0).then(|| Sample {
+                file_name: self.file_name.clone(),
+                chunk_number: self.chunk_number,
+                sentence: sentence.split_whitespace().join(" "),
+            });
+            for line in self.pairing.pairs(&words) {
+                *counts.entry(line).or_default() += 1;
+                if let Some(sample) = &sample {
+                    concordance.record(line, sample);
+                }
+            }
+        }
+        (counts, concordance)
+    }
+    pub fn calculate_name(&self) -> Result<String> {
+        let file_name = Path::new(&self.file_name)
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| Error::InvalidFileName(self.file_name.clone()))?;
+        let chunk_number = self.chunk_number.to_string();
+        Ok(match file_name.split_once('.') {
+            Some((name, extension)) => name.to_string() + "-" + &chunk_number + "." + extension,
+            None => file_name.to_string() + "-" + &chunk_number,
         })
-        .collect()
+    }
 }
@@ -0,0 +1,402 @@
+use itertools::Itertools;
+use std::{
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::{Error, Result};
+use crate::lease::Lease;
+use crate::registry::Registry;
+use crate::stats::Stats;
+use crate::storage::{Storage, StorageConfig, Store};
+
+// what looking for two answers to merge found
+pub enum Checkout {
+    Pair(Box<(Registry, Registry)>),
+    // other workers hold chunks or answers, so try again once they are done
+    Contended,
+    // at most one answer is left and nobody is working on anything
+    Done,
+}
+
+pub struct Bucket {
+    store: Store,
+    worker: String,
+}
+
+impl Bucket {
+    pub async fn new(config: StorageConfig) -> Self {
+        Bucket {
+            store: Store::open(config).await,
+            worker: worker_id(),
+        }
+    }
+
+    pub async fn bucket_does_not_exist(&self) -> Result<bool> {
+        Ok(!self.store.exists().await?)
+    }
+
+    pub async fn delete_chunk(&self, registry: Registry) -> Result<()> {
+        self.delete_from_bucket_top_level(&("singleprocessing/".to_owned() + &registry.name))
+            .await?;
+        self.release("chunks", &registry.name).await
+    }
+
+    pub async fn delete_answer(&self, registry: Registry) -> Result<()> {
+        self.delete_from_bucket_top_level(&("doubleprocessing/".to_owned() + &registry.name))
+            .await?;
+        self.release("answers", &registry.name).await
+    }
+
+    pub async fn delete_largest_answer(&self) -> Result<()> {
+        let f = self
+            .get_largest_file_name("answers")
+            .await?
+            .ok_or(Error::NoAnswer)?;
+        self.delete_from_bucket_top_level(&("answers/".to_owned() + &f))
+            .await
+    }
+
+    pub async fn save_answer(&self, ans: Registry) -> Result<()> {
+        let to_write = bincode::serialize(&ans)?;
+        let write_location = ans.name();
+
+        self.save_to_bucket_top_level(&("answers/".to_string() + write_location), to_write)
+            .await
+    }
+
+    // json, so the stats can be read without this crate. keyed by the pass's inputs, since a
+    // merged answer keeps the name of one of them
+    pub async fn save_stats(&self, inputs: &[&str], stats: &Stats) -> Result<()> {
+        let to_write = serde_json::to_vec_pretty(stats).expect("stats are plain data");
+        self.save_to_bucket_top_level(&("stats/".to_string() + &inputs.join("+")), to_write)
+            .await
+    }
+
+    pub async fn delete_from_bucket_top_level(&self, file_name: &str) -> Result<()> {
+        self.store.delete(file_name).await
+    }
+
+    pub async fn write_chunk(&self, book_chunk: Registry) -> Result<()> {
+        let to_write = bincode::serialize(&book_chunk)?;
+        let write_location = book_chunk.name;
+
+        self.save_to_bucket_top_level(&("chunks/".to_string() + &write_location), to_write)
+            .await
+    }
+
+    pub async fn checkout_smallest_chunk(&self) -> Result<Option<Registry>> {
+        let candidates = self.get_file_names_by_size("chunks").await?;
+        let f = self.claim_first(candidates.iter(), "chunks").await?;
+
+        if let Some(f) = f {
+            self.move_chunk(&f, "chunks", "singleprocessing/").await?;
+            Ok(Some(self.read_chunk(&f, "singleprocessing/").await?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn checkout_largest_and_smallest_answer(&self) -> Result<Checkout> {
+        let candidates = self.get_file_names_by_size("answers").await?;
+        let Some(s) = self.claim_first(candidates.iter(), "answers").await? else {
+            return self.nothing_to_merge().await;
+        };
+        let Some(l) = self
+            .claim_first(candidates.iter().rev().filter(|l| **l != s), "answers")
+            .await?
+        else {
+            self.release("answers", &s).await?;
+            return self.nothing_to_merge().await;
+        };
+
+        self.move_chunk(&s, "answers", "doubleprocessing/").await?;
+        self.move_chunk(&l, "answers", "doubleprocessing/").await?;
+        Ok(Checkout::Pair(Box::new((
+            self.read_chunk(&s, "doubleprocessing/").await?,
+            self.read_chunk(&l, "doubleprocessing/").await?,
+        ))))
+    }
+
+    // two answers could not be claimed. while any chunk or answer is still claimed, another
+    // worker may yet leave an answer behind that has to be merged
+    async fn nothing_to_merge(&self) -> Result<Checkout> {
+        for queue in ["chunks", "answers"] {
+            if !self
+                .store
+                .list(&("claims/".to_owned() + queue))
+                .await?
+                .is_empty()
+            {
+                return Ok(Checkout::Contended);
+            }
+        }
+        Ok(Checkout::Done)
+    }
+
+    // moves answers that could not be merged back to the queue and lets go of them
+    pub async fn return_answers(&self, names: &[&str]) -> Result<()> {
+        for name in names {
+            self.store
+                .rename(
+                    &("doubleprocessing/".to_owned() + name),
+                    &("answers/".to_owned() + name),
+                )
+                .await?;
+            self.release("answers", name).await?;
+        }
+        Ok(())
+    }
+
+    // the smallest registry anywhere in the store, queued or being worked on
+    pub async fn any_registry(&self) -> Result<Option<Registry>> {
+        let mut smallest: Option<(String, u64)> = None;
+        for prefix in ["chunks", "singleprocessing", "answers", "doubleprocessing"] {
+            for object in self.store.list(prefix).await? {
+                if smallest
+                    .as_ref()
+                    .is_none_or(|(_, size)| object.size < *size)
+                {
+                    smallest = Some((prefix.to_owned() + "/" + &object.name, object.size));
+                }
+            }
+        }
+        match smallest {
+            Some((key, _)) => Ok(Some(self.read_registry(&key).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn read_largest_chunk(&self) -> Result<Option<Registry>> {
+        let f = self.get_largest_file_name("answers").await?;
+
+        if let Some(l) = f {
+            Ok(Some(self.read_chunk(&l, "answers/").await?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn read_chunk(&self, f: &str, prefix: &str) -> Result<Registry> {
+        self.read_registry(&(prefix.to_string() + f)).await
+    }
+
+    pub async fn read_registry(&self, key: &str) -> Result<Registry> {
+        let data = self.store.read(key).await?;
+        bincode::deserialize(&data).map_err(|source| Error::Corrupt {
+            key: key.to_string(),
+            source,
+        })
+    }
+
+    // (prefix, object count, total bytes) for each queue
+    pub async fn status(&self) -> Result<Vec<(&'static str, usize, u64)>> {
+        let mut res = vec![];
+        for prefix in ["chunks", "answers", "singleprocessing", "doubleprocessing"] {
+            let objects = self.store.list(prefix).await?;
+            res.push((prefix, objects.len(), objects.iter().map(|o| o.size).sum()));
+        }
+        Ok(res)
+    }
+
+    async fn get_file_names_by_size(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self
+            .store
+            .list(prefix)
+            .await?
+            .into_iter()
+            .sorted_by_key(|o| o.size)
+            .map(|o| o.name)
+            .collect())
+    }
+
+    // a claim marker holds a lease and stays in place until the object is deleted after
+    // processing, so whoever writes it first owns the object
+    async fn claim_first(
+        &self,
+        candidates: impl Iterator<Item = &String>,
+        prefix: &str,
+    ) -> Result<Option<String>> {
+        for name in candidates {
+            let claimed = self
+                .store
+                .write_if_absent(
+                    &claim_key(prefix, name),
+                    bincode::serialize(&Lease::new(&self.worker))?,
+                )
+                .await?;
+            if claimed {
+                // the listing may be stale, and the previous owner may have finished and released it
+                if self.store.has(&(prefix.to_owned() + "/" + name)).await? {
+                    return Ok(Some(name.clone()));
+                }
+                self.release(prefix, name).await?;
+            }
+        }
+        Ok(None)
+    }
+
+    // returns objects stranded in a processing prefix by a worker that died to their queue.
+    // anything whose lease is missing or older than lease is considered stranded
+    pub async fn recover(&self, lease: Duration) -> Result<Vec<String>> {
+        let mut recovered = vec![];
+        for (queue, processing) in [
+            ("chunks", "singleprocessing"),
+            ("answers", "doubleprocessing"),
+        ] {
+            let claimed = self.store.list(&("claims/".to_owned() + queue)).await?;
+            let stranded = self.store.list(processing).await?;
+            let names = claimed
+                .into_iter()
+                .chain(stranded)
+                .map(|o| o.name)
+                .unique()
+                .collect_vec();
+
+            for name in names {
+                if self.lease_is_live(queue, &name, lease).await? {
+                    continue;
+                }
+                let processing_key = processing.to_owned() + "/" + &name;
+                let queue_key = queue.to_owned() + "/" + &name;
+                if self.store.has(&processing_key).await? {
+                    // a newer object of the same name already superseded this copy, or its
+                    // chunks made it into an answer that was saved before the worker died
+                    if self.store.has(&queue_key).await?
+                        || self.merged_into_an_answer(&processing_key).await?
+                    {
+                        self.store.delete(&processing_key).await?;
+                    } else {
+                        self.store.rename(&processing_key, &queue_key).await?;
+                        recovered.push(queue_key);
+                    }
+                }
+                if self.store.has(&claim_key(queue, &name)).await? {
+                    self.release(queue, &name).await?;
+                }
+            }
+        }
+        Ok(recovered)
+    }
+
+    // whether an answer, saved or being merged, already holds every chunk of the stranded object
+    async fn merged_into_an_answer(&self, processing_key: &str) -> Result<bool> {
+        let stranded = self.read_registry(processing_key).await?;
+        for prefix in ["answers/", "doubleprocessing/"] {
+            for answer in self.store.list(prefix).await? {
+                if prefix.to_owned() + &answer.name == processing_key {
+                    continue;
+                }
+                let answer = self.read_chunk(&answer.name, prefix).await?;
+                if stranded
+                    .provenance
+                    .iter()
+                    .all(|source| answer.provenance.contains(source))
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    async fn lease_is_live(&self, prefix: &str, name: &str, duration: Duration) -> Result<bool> {
+        Ok(self
+            .read_lease(prefix, name)
+            .await?
+            .is_some_and(|lease| !lease.expired(duration)))
+    }
+
+    // none when the claim has been released
+    async fn read_lease(&self, prefix: &str, name: &str) -> Result<Option<Lease>> {
+        let key = claim_key(prefix, name);
+        let data = match self.store.read(&key).await {
+            Ok(data) => data,
+            Err(Error::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        bincode::deserialize(&data)
+            .map(Some)
+            .map_err(|source| Error::Corrupt { key, source })
+    }
+
+    // rewrites this worker's claims with a fresh lease, so that recover leaves the objects
+    // alone for as long as they are being worked on. a claim is only rewritten while it still
+    // has half its lease to run, so the write cannot land after another worker has recovered
+    // and claimed it, and it is read back in case it did anyway
+    pub async fn renew(&self, prefix: &str, names: &[&str], lease: Duration) -> Result<()> {
+        for name in names {
+            let held = self.read_lease(prefix, name).await?;
+            if held.is_none_or(|held| held.worker != self.worker || held.expired(lease / 2)) {
+                return Err(Error::Lost(claim_key(prefix, name)));
+            }
+            self.store
+                .write(
+                    &claim_key(prefix, name),
+                    bincode::serialize(&Lease::new(&self.worker))?,
+                )
+                .await?;
+        }
+        self.confirm(prefix, names).await
+    }
+
+    // fails if any of the claims has been recovered, and maybe taken, by another worker
+    pub async fn confirm(&self, prefix: &str, names: &[&str]) -> Result<()> {
+        for name in names {
+            let lease = self.read_lease(prefix, name).await?;
+            if lease.is_none_or(|lease| lease.worker != self.worker) {
+                return Err(Error::Lost(claim_key(prefix, name)));
+            }
+        }
+        Ok(())
+    }
+
+    async fn release(&self, prefix: &str, name: &str) -> Result<()> {
+        self.store.delete(&claim_key(prefix, name)).await
+    }
+
+    async fn get_largest_file_name(&self, prefix: &str) -> Result<Option<String>> {
+        Ok(self
+            .store
+            .list(prefix)
+            .await?
+            .into_iter()
+            .max_by(|x, y| x.size.cmp(&y.size))
+            .map(|o| o.name))
+    }
+
+    async fn move_chunk(
+        &self,
+        file_name: &str,
+        prefix: &str,
+        processing_prefix: &str,
+    ) -> Result<()> {
+        self.store
+            .rename(
+                &(prefix.to_owned() + "/" + file_name),
+                &(processing_prefix.to_owned() + file_name),
+            )
+            .await
+    }
+
+    pub async fn save_to_bucket_top_level(&self, file_name: &str, body: Vec<u8>) -> Result<()> {
+        self.store.write(file_name, body).await
+    }
+
+    pub async fn create_bucket(&self) -> Result<()> {
+        self.store.create().await
+    }
+}
+
+fn claim_key(prefix: &str, name: &str) -> String {
+    "claims/".to_owned() + prefix + "/" + name
+}
+
+fn worker_id() -> String {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+    format!("{}-{}-{}", host, process::id(), started)
+}
@@ -0,0 +1,137 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+
+use crate::tokenizer::TERMINATORS;
+
+// how add cuts a book into chunks. written and parsed as a spec, e.g. "sentences:200",
+// which is also what gets recorded in each chunk's provenance
+#[derive(Clone, Debug)]
+pub enum Chunker {
+    // a new chunk starts at every match, so the heading stays with the text it introduces
+    Heading(Regex),
+    Sentences(usize),
+    // whole sentences are packed into chunks of at most this many bytes, unless a single
+    // sentence is longer
+    Bytes(usize),
+    // groups of paragraphs, which are separated by blank lines
+    Paragraphs(usize),
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Chunker::Heading(Regex::new(DEFAULT_HEADING).expect("the default heading is valid"))
+    }
+}
+
+const DEFAULT_HEADING: &str = r"(?im)^[ \t]*chapter\b";
+
+impl Chunker {
+    pub fn chunks<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let cuts = match self {
+            Chunker::Heading(heading) => heading.find_iter(text).map(|m| m.start()).collect(),
+            Chunker::Sentences(count) => every(sentence_ends(text), *count),
+            Chunker::Bytes(size) => pack(sentence_ends(text), *size),
+            Chunker::Paragraphs(count) => every(paragraph_ends(text), *count),
+        };
+        split_at(text, cuts)
+    }
+}
+
+fn split_at(text: &str, cuts: Vec<usize>) -> Vec<&str> {
+    // whatever follows the last word belongs with the chunk before it
+    let last_word = text
+        .char_indices()
+        .rfind(|(_, c)| c.is_alphabetic())
+        .map(|(index, _)| index)
+        .unwrap_or_default();
+    let mut chunks = vec![];
+    let mut start = 0;
+    for cut in cuts {
+        if cut > start && cut <= last_word {
+            chunks.push(&text[start..cut]);
+            start = cut;
+        }
+    }
+    // text before the first heading is kept as a chunk of its own
+    if start < text.len() || chunks.is_empty() {
+        chunks.push(&text[start..]);
+    }
+    chunks
+}
+
+// byte offsets just past each sentence that has a word in it
+fn sentence_ends(text: &str) -> Vec<usize> {
+    let mut ends = vec![];
+    let mut offset = 0;
+    for sentence in text.split_inclusive(TERMINATORS) {
+        offset += sentence.len();
+        if sentence.chars().any(|c| c.is_alphabetic()) {
+            ends.push(offset);
+        }
+    }
+    ends
+}
+
+// byte offsets just past each paragraph, where a paragraph ends at a blank line
+fn paragraph_ends(text: &str) -> Vec<usize> {
+    let blank = Regex::new(r"\n[ \t\r]*\n").expect("the paragraph break is valid");
+    blank.find_iter(text).map(|m| m.end()).collect()
+}
+
+fn every(ends: Vec<usize>, count: usize) -> Vec<usize> {
+    ends.into_iter().skip(count - 1).step_by(count).collect()
+}
+
+fn pack(ends: Vec<usize>, size: usize) -> Vec<usize> {
+    let mut cuts = vec![];
+    let mut start = 0;
+    let mut last = 0;
+    for end in ends {
+        if end - start > size && last > start {
+            cuts.push(last);
+            start = last;
+        }
+        last = end;
+    }
+    cuts
+}
+
+impl fmt::Display for Chunker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chunker::Heading(heading) => write!(f, "heading:{}", heading),
+            Chunker::Sentences(count) => write!(f, "sentences:{}", count),
+            Chunker::Bytes(size) => write!(f, "bytes:{}", size),
+            Chunker::Paragraphs(count) => write!(f, "paragraphs:{}", count),
+        }
+    }
+}
+
+impl FromStr for Chunker {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let (kind, argument) = spec.split_once(':').unwrap_or((spec, ""));
+        let count = || match argument.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!(
+                "{} needs a positive number, e.g. {}:100",
+                kind, kind
+            )),
+        };
+        match kind {
+            "heading" if argument.is_empty() => Ok(Chunker::default()),
+            "heading" => Regex::new(argument)
+                .map(Chunker::Heading)
+                .map_err(|e| e.to_string()),
+            "sentences" => Ok(Chunker::Sentences(count()?)),
+            "bytes" => Ok(Chunker::Bytes(count()?)),
+            "paragraphs" => Ok(Chunker::Paragraphs(count()?)),
+            _ => Err(format!(
+                "unknown chunker {}, expected heading, sentences, bytes or paragraphs",
+                kind
+            )),
+        }
+    }
+}
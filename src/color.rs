@@ -1,5 +1,8 @@
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum Color {
     Black,
     Red,
     Both,
+    // eligible only once both sides' counts are added together
+    Joined,
 }
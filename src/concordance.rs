@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::line::Line;
+use crate::vocabulary::WordId;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Sample {
+    pub file_name: String,
+    pub chunk_number: usize,
+    pub sentence: String,
+}
+
+// up to limit sentences that produced each pair. empty unless asked for when adding
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct Concordance {
+    limit: usize,
+    samples: HashMap<Line, Vec<Sample>>,
+}
+
+impl Concordance {
+    pub(crate) fn new(limit: usize) -> Self {
+        Concordance {
+            limit,
+            samples: HashMap::default(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, line: Line, sample: &Sample) {
+        if self.limit == 0 {
+            return;
+        }
+        let samples = self.samples.entry(line).or_default();
+        if samples.len() < self.limit && !samples.contains(sample) {
+            samples.push(sample.clone());
+        }
+    }
+
+    pub fn samples(&self, line: &Line) -> &[Sample] {
+        self.samples
+            .get(line)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    // takes in other's samples, whose words are mapped to ours, keeping the larger limit
+    pub(crate) fn absorb(&mut self, other: &Self, mapping: &[WordId]) {
+        self.limit = self.limit.max(other.limit);
+        for (line, samples) in &other.samples {
+            for sample in samples {
+                self.record(line.remap(mapping), sample);
+            }
+        }
+    }
+}
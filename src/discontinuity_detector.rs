@@ -1,44 +1,63 @@
+use std::collections::HashMap;
+
 use crate::color::Color;
 use crate::line::Line;
+use crate::ortho::Ortho;
 use crate::registry::Registry;
 
 pub struct DiscontinuityDetector {
-    source_only: Registry,
-    destination_only: Registry,
+    source: HashMap<Line, u64>,
+    destination: HashMap<Line, u64>,
+    min_count: u64,
 }
 impl DiscontinuityDetector {
-    pub(crate) fn new(source_answer: &Registry, target_answer: &Registry) -> Self {
+    // colours are looked up by id, so both sides are re-expressed in the combined registry's
+    // vocabulary
+    pub(crate) fn new(
+        combined: &Registry,
+        source_answer: &Registry,
+        target_answer: &Registry,
+        min_count: u64,
+    ) -> Self {
         DiscontinuityDetector {
-            source_only: source_answer.minus(target_answer),
-            destination_only: target_answer.minus(source_answer),
+            source: combined.counts_of(source_answer),
+            destination: combined.counts_of(target_answer),
+            min_count,
         }
     }
 
-    pub(crate) fn discontinuity(&self, lhs: &Line, line: &Line, rhs: &Line) -> bool {
-        match (self.color(lhs), self.color(line), self.color(rhs)) {
-            (Color::Black, Color::Black, Color::Red) => true,
-            (Color::Black, Color::Red, Color::Black) => true,
-            (Color::Black, Color::Red, Color::Red) => true,
-            (Color::Black, Color::Red, Color::Both) => true,
-            (Color::Black, Color::Both, Color::Red) => true,
-            (Color::Red, Color::Black, Color::Black) => true,
-            (Color::Red, Color::Black, Color::Both) => true,
-            (Color::Red, Color::Black, Color::Red) => true,
-            (Color::Red, Color::Red, Color::Black) => true,
-            (Color::Red, Color::Both, Color::Black) => true,
-            (Color::Both, Color::Black, Color::Red) => true,
-            (Color::Both, Color::Red, Color::Black) => true,
-            _ => false,
-        }
+    // the combined registry's eligible lines that are not eligible on both sides. every new
+    // square has at least one of them as an edge
+    pub(crate) fn new_lines(&self, combined: &Registry) -> Vec<Line> {
+        combined
+            .pairs
+            .iter()
+            .filter(|(line, count)| **count >= self.min_count && self.color(line) != Color::Both)
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    pub(crate) fn discontinuity(&self, square: &Ortho) -> bool {
+        // the square was already found by one side if every edge was eligible on that side,
+        // so it is only new when neither side could form it alone
+        let colors = square.edges().map(|edge| self.color(&edge));
+        let source_lacks = colors
+            .iter()
+            .any(|c| matches!(c, Color::Red | Color::Joined));
+        let destination_lacks = colors
+            .iter()
+            .any(|c| matches!(c, Color::Black | Color::Joined));
+        source_lacks && destination_lacks
     }
 
     fn color(&self, lhs: &Line) -> Color {
-        if self.source_only.contains(lhs) {
-            Color::Black
-        } else if self.destination_only.contains(lhs) {
-            Color::Red
-        } else {
-            Color::Both
+        let eligible =
+            |side: &HashMap<Line, u64>| side.get(lhs).is_some_and(|count| *count >= self.min_count);
+        match (eligible(&self.source), eligible(&self.destination)) {
+            (true, false) => Color::Black,
+            (false, true) => Color::Red,
+            (true, true) => Color::Both,
+            (false, false) => Color::Joined,
         }
     }
 }
@@ -0,0 +1,90 @@
+use std::{fmt, io};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    // the text file handed to add could not be read
+    Input {
+        file_name: String,
+        source: io::Error,
+    },
+    InvalidFileName(String),
+    // the storage backend failed, e.g. the endpoint is unreachable or a request was refused
+    Storage(String),
+    NotFound(String),
+    // the claim on an object was recovered by another worker while this one held it
+    Lost(String),
+    // an object was read but is not a serialized Registry or lease
+    Corrupt {
+        key: String,
+        source: bincode::Error,
+    },
+    Encode(bincode::Error),
+    NoAnswer,
+    // two registries were made in ways that cannot be merged, e.g. by different tokenizers
+    Incompatible(String),
+    // results could not be written out, e.g. stdout was closed
+    Output(io::Error),
+    // the thread pool folding runs on could not be started
+    Threads(String),
+}
+
+impl Error {
+    pub(crate) fn storage(context: impl fmt::Display) -> Self {
+        Error::Storage(context.to_string())
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Input { .. } | Error::InvalidFileName(_) => 2,
+            Error::Storage(_) | Error::NotFound(_) | Error::Lost(_) => 3,
+            Error::Corrupt { .. } | Error::Encode(_) => 4,
+            Error::NoAnswer => 5,
+            Error::Incompatible(_) => 6,
+            Error::Output(_) | Error::Threads(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Input { file_name, source } => {
+                write!(f, "cannot read {}: {}", file_name, source)
+            }
+            Error::InvalidFileName(file_name) => write!(f, "{} does not name a file", file_name),
+            Error::Storage(message) => write!(f, "storage error: {}", message),
+            Error::NotFound(key) => write!(f, "{} does not exist", key),
+            Error::Lost(key) => write!(f, "{} was taken over by another worker", key),
+            Error::Corrupt { key, source } => write!(f, "{} is not readable: {}", key, source),
+            Error::Encode(source) => write!(f, "cannot serialize: {}", source),
+            Error::NoAnswer => write!(f, "there are no answers yet"),
+            Error::Incompatible(message) => write!(f, "cannot merge: {}", message),
+            Error::Output(source) => write!(f, "cannot write results: {}", source),
+            Error::Threads(message) => write!(f, "cannot start worker threads: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Input { source, .. } | Error::Output(source) => Some(source),
+            Error::Corrupt { source, .. } | Error::Encode(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(source: bincode::Error) -> Self {
+        Error::Encode(source)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Output(source)
+    }
+}
@@ -1,8 +1,14 @@
-use std::path::Path;
+use std::fs::read_to_string;
 
-use aws_sdk_s3::primitives::ByteStream;
-use futures::executor::block_on;
+use crate::error::{Error, Result};
 
-pub fn read_file(file_name: &String) -> ByteStream {
-    block_on(ByteStream::from_path(Path::new(&file_name))).unwrap()
+pub fn read_text(file_name: &str) -> Result<String> {
+    read_to_string(file_name).map_err(|source| input_error(file_name, source))
+}
+
+fn input_error(file_name: &str, source: std::io::Error) -> Error {
+    Error::Input {
+        file_name: file_name.to_string(),
+        source,
+    }
 }
@@ -1,88 +1,187 @@
+use crate::error::Result;
+use crate::hyper_ortho::HyperOrtho;
 use crate::line::Line;
+use crate::stats::Stats;
+use crate::vocabulary::WordId;
 use crate::{discontinuity_detector::DiscontinuityDetector, ortho::Ortho, registry::Registry};
-use itertools::iproduct;
+use itertools::Itertools;
+use rayon::prelude::*;
+use std::collections::HashSet;
 
-pub fn single_process(registry: &Registry) -> Registry {
-    let new_squares = ffbb(registry);
-    registry.add(new_squares)
+// min_count is how many times each edge must have been seen before a square is formed from it.
+// extend is how many extension passes grow the squares into larger orthos
+pub fn single_process(registry: &Registry, min_count: u64, extend: usize) -> (Registry, Stats) {
+    let mut stats = Stats::default();
+    let (triples, new_squares) = stats.time("squares", || ffbb(registry, min_count));
+    // each square is reached once from each orientation, so only distinct ones are counted
+    let new_squares: HashSet<Ortho> = new_squares.into_iter().collect();
+    stats.triples_examined = triples;
+    stats.candidate_squares = new_squares.len() as u64;
+    let with_squares = registry.add(new_squares.into_iter().collect());
+    let seeds = with_squares
+        .squares
+        .iter()
+        .copied()
+        .map(HyperOrtho::from)
+        .collect_vec();
+    let mut ans = stats.time("orthos", || grow(&with_squares, seeds, min_count, extend));
+    ans.min_count = Some(min_count);
+    stats.count_new(registry, &ans);
+    (ans, stats)
 }
 
-pub fn merge_process(source_answer: &Registry, target_answer: &Registry) -> Registry {
-    let detector = DiscontinuityDetector::new(source_answer, target_answer);
-    let both = source_answer.union(target_answer);
-    let mut check_back = vec![];
-    let mut total: u128 = 0;
-    let mut hit = 0;
-    for line in both.get_lines() {
-        let lhss = both.left_of(line);
-        let rhss = both.right_of(&line);
+pub fn merge_process(
+    source_answer: &Registry,
+    target_answer: &Registry,
+    min_count: u64,
+    extend: usize,
+) -> Result<(Registry, Stats)> {
+    // each side is assumed to hold every square its edges allow at this min_count
+    source_answer.check_folded_with(min_count)?;
+    target_answer.check_folded_with(min_count)?;
+    let mut stats = Stats::default();
+    let both = stats.time("union", || source_answer.union(target_answer))?;
+    let detector = DiscontinuityDetector::new(&both, source_answer, target_answer, min_count);
+    // a square whose edges are all coloured Both was already found by each side, so only
+    // squares through a line new to one side are looked at. every such line is independent
+    let new_lines = detector.new_lines(&both);
+    let (triples, candidates) = stats.time("squares", || {
+        gather(
+            new_lines
+                .par_iter()
+                .map(|line| squares_through(&both, line, min_count)),
+        )
+    });
+    // a square is reached through each of its new edges, so only distinct ones are counted
+    let candidates: HashSet<Ortho> = candidates.into_iter().collect();
+    stats.triples_examined = triples;
+    stats.candidate_squares = candidates.len() as u64;
+    let additional_squares = candidates
+        .into_iter()
+        .filter(|square| detector.discontinuity(square))
+        .collect_vec();
+    stats.discontinuities = additional_squares.len() as u64;
 
-        for (lhs, rhs) in iproduct!(lhss, rhss) {
-            total += 1;
-            if detector.discontinuity(&lhs, &line, &rhs) {
-                hit += 1;
-                check_back.push((lhs, line, rhs));
-            }
-        }
-    }
-    if total != 0 {
-        dbg!(hit * 100 / total);
-    } else {
-        dbg!(total);
-    }
-    
+    // orthos either side already holds are kept as they are. a new one grows out of a new
+    // square, or out of an old square or ortho by a row holding a new line. that row starts
+    // one step past a word of the old one, so only old ones next to a new line can grow
+    let touched: HashSet<WordId> = new_lines
+        .iter()
+        .flat_map(|line| [line.first, line.second])
+        .collect();
+    let near: HashSet<WordId> = touched
+        .iter()
+        .flat_map(|word| both.backward(*word).copied())
+        .collect();
+    let seeds = additional_squares
+        .iter()
+        .copied()
+        .map(HyperOrtho::from)
+        .chain(
+            both.squares
+                .iter()
+                .copied()
+                .map(HyperOrtho::from)
+                .chain(both.orthos.iter().cloned())
+                .filter(|ortho| ortho.depth() < extend)
+                .filter(|ortho| ortho.words().iter().any(|word| near.contains(word))),
+        )
+        .collect_vec();
+    let with_squares = both.add(additional_squares);
+    let ans = stats.time("orthos", || grow(&with_squares, seeds, min_count, extend));
+    stats.count_new(&both, &ans);
+    Ok((ans, stats))
+}
 
-    let additional_squares = find_additional_squares(&both, check_back);
-    both.add(additional_squares)
+// sums the triples walked and collects the squares found for each line
+fn gather(per_line: impl ParallelIterator<Item = (u64, Vec<Ortho>)>) -> (u64, Vec<Ortho>) {
+    per_line.reduce(
+        || (0, vec![]),
+        |(triples, mut squares), (more_triples, more_squares)| {
+            squares.extend(more_squares);
+            (triples + more_triples, squares)
+        },
+    )
 }
 
-fn find_additional_squares(
-    combined_book: &Registry,
-    check_back: Vec<(&Line, &Line, &Line)>,
-) -> Vec<Ortho> {
-    // left: a-b
-    // center: a-c
-    // right: c-d
-    // a-b
-    // |
-    // c-d
+// extends the seeds, and everything grown from them, until each is extend extensions away from
+// its square. orthos the registry already holds are not grown again
+fn grow(registry: &Registry, seeds: Vec<HyperOrtho>, min_count: u64, extend: usize) -> Registry {
+    let mut found: HashSet<HyperOrtho> = registry.orthos.clone();
+    let mut grown = vec![];
+    let mut frontier = seeds;
+    while !frontier.is_empty() {
+        frontier = frontier
+            .par_iter()
+            .filter(|ortho| ortho.depth() < extend)
+            .flat_map_iter(|ortho| ortho.extensions(registry, min_count))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter(|ortho| found.insert(ortho.clone()))
+            .collect_vec();
+        grown.extend(frontier.iter().cloned());
+    }
+    registry.add_orthos(grown)
+}
 
-    // verify b != c
-    // verify b -> d
+// every square with the line as any of its four edges, made of edges seen at least min_count
+// times.
+// a-b
+// | |
+// c-d
+fn squares_through(book: &Registry, line: &Line, min_count: u64) -> (u64, Vec<Ortho>) {
+    let edge = |first, second| book.contains_line_with(first, second, min_count);
+    let (first, second) = (line.first, line.second);
+    let mut triples = 0;
     let mut res = vec![];
-    for (left, center, right) in check_back.iter() {
-        if left.second != center.second {
-            if combined_book.contains_line_with(&left.second, &right.second) {
-                res.push(Ortho::new(
-                    left.first.to_string(),
-                    left.second.to_string(),
-                    right.first.clone(),
-                    right.second.clone(),
-                ))
+    // with the line on top: the other edge out of a, then the corner past both. the mirrored
+    // square with the line on the left has the same canonical form
+    for &other in book
+        .forward(first)
+        .filter(|&&o| o != second && edge(first, o))
+    {
+        for &d in book.forward(second).filter(|&&d| edge(second, d)) {
+            triples += 1;
+            if edge(other, d) {
+                res.push(Ortho::new(first, second, other, d).canonical(&book.vocabulary));
             }
         }
     }
-
-    res
-}
-
-fn ffbb(book: &Registry) -> Vec<Ortho> {
-    let mut res = vec![];
-    for line in book.pairs.clone() {
-        let a = line.first;
-        let b = line.second;
-        for d in book.forward(b.clone()) {
-            for c in book.backward(d.clone()) {
-                if b != c && book.backward(c.clone()).contains(&a) {
-                    res.push(Ortho::new(
-                        a.to_string(),
-                        b.to_string(),
-                        c.clone(),
-                        d.clone(),
-                    ))
-                }
+    // with the line on the right: the other edge into d, then the corner before both. again
+    // this covers the line at the bottom too
+    for &other in book
+        .backward(second)
+        .filter(|&&o| o != first && edge(o, second))
+    {
+        for &a in book.backward(first).filter(|&&a| edge(a, first)) {
+            triples += 1;
+            if edge(a, other) {
+                res.push(Ortho::new(a, first, other, second).canonical(&book.vocabulary));
             }
         }
     }
-    res
+    (triples, res)
+}
+
+fn ffbb(book: &Registry, min_count: u64) -> (u64, Vec<Ortho>) {
+    let edge = |first, second| book.contains_line_with(first, second, min_count);
+    gather(
+        book.pairs
+            .par_iter()
+            .filter(|(_, count)| **count >= min_count)
+            .map(|(line, _)| {
+                let (a, b) = (line.first, line.second);
+                let mut triples = 0;
+                let mut res = vec![];
+                for &d in book.forward(b).filter(|&&d| edge(b, d)) {
+                    for &c in book.backward(d) {
+                        triples += 1;
+                        if b != c && edge(c, d) && edge(a, c) {
+                            res.push(Ortho::new(a, b, c, d).canonical(&book.vocabulary));
+                        }
+                    }
+                }
+                (triples, res)
+            }),
+    )
 }
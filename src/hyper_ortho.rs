@@ -0,0 +1,263 @@
+use std::fmt;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::line::Line;
+use crate::ortho::Ortho;
+use crate::registry::Registry;
+use crate::vocabulary::{Vocabulary, WordId};
+
+// an ortho of any shape, e.g. [2, 3] for two rows of three words or [2, 2, 2] for a cube.
+// words are kept in row-major order. every word is paired with its successor along each
+// axis, and words the same number of steps from the origin are all distinct
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Hash, Eq)]
+pub struct HyperOrtho {
+    shape: Vec<usize>,
+    words: Vec<WordId>,
+}
+
+impl HyperOrtho {
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn words(&self) -> &[WordId] {
+        &self.words
+    }
+
+    pub(crate) fn remap(&self, mapping: &[WordId]) -> Self {
+        HyperOrtho {
+            shape: self.shape.clone(),
+            words: self.words.iter().map(|w| mapping[*w as usize]).collect(),
+        }
+    }
+
+    // the ortho with its ids resolved, for printing
+    pub fn with_words<'a>(&'a self, vocabulary: &'a Vocabulary) -> HyperOrthoWords<'a> {
+        HyperOrthoWords::new(
+            &self.shape,
+            self.words.iter().map(|w| vocabulary.word(*w)).collect(),
+        )
+    }
+
+    // every pair the ortho is built from, each successor along each axis
+    pub fn edges(&self) -> Vec<Line> {
+        let mut res = vec![];
+        for (index, &second) in self.words.iter().enumerate() {
+            let position = coordinates(&self.shape, index);
+            for axis in 0..self.shape.len() {
+                if position[axis] > 0 {
+                    let first = self.words[offset(&self.shape, &step_back(&position, axis))];
+                    res.push(Line { first, second });
+                }
+            }
+        }
+        res
+    }
+
+    // how many extensions away from a square the ortho is. each one adds a row along an
+    // axis or a new axis of length two, so a 2x3 and a 2x2x2 are both one away
+    pub(crate) fn depth(&self) -> usize {
+        self.shape.iter().map(|length| length - 2).sum::<usize>() + self.shape.len() - 2
+    }
+
+    // every ortho one row longer along an existing axis, or one dimension deeper, that the
+    // registry's pairs seen at least min_count times support
+    pub(crate) fn extensions(&self, registry: &Registry, min_count: u64) -> Vec<HyperOrtho> {
+        (0..=self.shape.len())
+            .flat_map(|axis| self.extend(axis, registry, min_count))
+            .map(|ortho| ortho.canonical(&registry.vocabulary))
+            .collect()
+    }
+
+    // reordering the axes gives the same ortho, so of every order only the one with the
+    // shortest axes first, and then the words that sort first, is kept. for a square this is
+    // the orientation Ortho::canonical keeps
+    pub(crate) fn canonical(self, vocabulary: &Vocabulary) -> Self {
+        (0..self.shape.len())
+            .permutations(self.shape.len())
+            .map(|axes| self.permuted(&axes))
+            .min_by(|x, y| {
+                x.shape.cmp(&y.shape).then_with(|| {
+                    x.words
+                        .iter()
+                        .map(|w| vocabulary.word(*w))
+                        .cmp(y.words.iter().map(|w| vocabulary.word(*w)))
+                })
+            })
+            .expect("an ortho has at least one axis")
+    }
+
+    // the same ortho with axis k of the result being axis axes[k] of this one
+    fn permuted(&self, axes: &[usize]) -> Self {
+        let shape = axes.iter().map(|axis| self.shape[*axis]).collect_vec();
+        let words = (0..self.words.len())
+            .map(|index| {
+                let position = coordinates(&shape, index);
+                let mut original = vec![0; axes.len()];
+                for (k, axis) in axes.iter().enumerate() {
+                    original[*axis] = position[k];
+                }
+                self.words[offset(&self.shape, &original)]
+            })
+            .collect();
+        HyperOrtho { shape, words }
+    }
+
+    fn extend(&self, axis: usize, registry: &Registry, min_count: u64) -> Vec<HyperOrtho> {
+        let mut shape = self.shape.clone();
+        if axis == shape.len() {
+            shape.push(2);
+        } else {
+            shape[axis] += 1;
+        }
+        let size = shape.iter().product();
+        let mut words = vec![None; size];
+        for (index, word) in self.words.iter().enumerate() {
+            let mut position = coordinates(&self.shape, index);
+            position.resize(shape.len(), 0);
+            words[offset(&shape, &position)] = Some(*word);
+        }
+        // the new slice, in row-major order so every predecessor of a position is already known
+        let open = (0..size)
+            .filter(|index| words[*index].is_none())
+            .collect_vec();
+
+        let mut res = vec![];
+        fill(
+            &shape, &mut words, &open, axis, registry, min_count, &mut res,
+        );
+        res
+    }
+}
+
+fn fill(
+    shape: &[usize],
+    words: &mut Vec<Option<WordId>>,
+    open: &[usize],
+    axis: usize,
+    registry: &Registry,
+    min_count: u64,
+    res: &mut Vec<HyperOrtho>,
+) {
+    let Some((&index, rest)) = open.split_first() else {
+        res.push(HyperOrtho {
+            shape: shape.to_vec(),
+            words: words
+                .iter()
+                .map(|w| w.expect("every position is filled"))
+                .collect(),
+        });
+        return;
+    };
+    let position = coordinates(shape, index);
+    let predecessors = (0..shape.len())
+        .filter(|k| position[*k] > 0)
+        .map(|k| words[offset(shape, &step_back(&position, k))].expect("filled in order"))
+        .collect_vec();
+    let distance: usize = position.iter().sum();
+    let diagonal = (0..words.len())
+        .filter(|other| coordinates(shape, *other).iter().sum::<usize>() == distance)
+        .filter_map(|other| words[other])
+        .collect_vec();
+
+    let along = words[offset(shape, &step_back(&position, axis))].expect("filled in order");
+    let candidates = registry.forward(along).copied().collect_vec();
+    for candidate in candidates {
+        let fits = !diagonal.contains(&candidate)
+            && predecessors
+                .iter()
+                .all(|first| registry.contains_line_with(*first, candidate, min_count));
+        if fits {
+            words[index] = Some(candidate);
+            fill(shape, words, rest, axis, registry, min_count, res);
+            words[index] = None;
+        }
+    }
+}
+
+fn coordinates(shape: &[usize], mut index: usize) -> Vec<usize> {
+    let mut position = vec![0; shape.len()];
+    for axis in (0..shape.len()).rev() {
+        position[axis] = index % shape[axis];
+        index /= shape[axis];
+    }
+    position
+}
+
+fn offset(shape: &[usize], position: &[usize]) -> usize {
+    shape
+        .iter()
+        .zip(position)
+        .fold(0, |acc, (length, coordinate)| acc * length + coordinate)
+}
+
+fn step_back(position: &[usize], axis: usize) -> Vec<usize> {
+    let mut res = position.to_vec();
+    res[axis] -= 1;
+    res
+}
+
+// a b
+// c d
+// is the shape [2, 2] with words a b c d
+impl From<Ortho> for HyperOrtho {
+    fn from(square: Ortho) -> Self {
+        HyperOrtho {
+            shape: vec![2, 2],
+            words: vec![square.a(), square.b(), square.c(), square.d()],
+        }
+    }
+}
+
+pub struct HyperOrthoWords<'a> {
+    shape: &'a [usize],
+    words: Vec<&'a str>,
+}
+
+impl<'a> HyperOrthoWords<'a> {
+    pub(crate) fn new(shape: &'a [usize], words: Vec<&'a str>) -> Self {
+        HyperOrthoWords { shape, words }
+    }
+}
+
+// 2x3 for two rows of three words
+pub(crate) fn shape_name(shape: &[usize]) -> String {
+    shape.iter().join("x")
+}
+
+// rows of the last axis, with a blank line between 2d slices of higher dimensional orthos.
+// columns are padded so that words line up
+impl fmt::Display for HyperOrthoWords<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.shape.last().copied().unwrap_or(1);
+        let rows = self.shape.iter().rev().nth(1).copied().unwrap_or(1);
+        let widths = (0..columns)
+            .map(|column| {
+                self.words
+                    .iter()
+                    .skip(column)
+                    .step_by(columns)
+                    .map(|w| w.chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect_vec();
+        for (index, row) in self.words.chunks(columns).enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+                if index % rows == 0 {
+                    writeln!(f)?;
+                }
+            }
+            let cells = row
+                .iter()
+                .zip(&widths)
+                .map(|(word, width)| format!("{:<width$}", word, width = width))
+                .join(" ");
+            write!(f, "{}", cells.trim_end())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use crate::chunker::Chunker;
+use crate::pairing::Pairing;
+use crate::tokenizer::Tokenization;
+
+// how a book is read into chunk registries
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct Ingestion {
+    /// How to cut the book into chunks: heading[:REGEX], sentences:N, bytes:N or paragraphs:N
+    #[arg(short, long, default_value = "heading")]
+    pub chunker: Chunker,
+
+    /// How to split chunks into sentences and words
+    #[arg(short, long, value_enum, default_value_t = Tokenization::Classic)]
+    pub tokenizer: Tokenization,
+
+    /// Which words of a sentence to pair: adjacent, distance:K or window:K
+    #[arg(short, long, default_value = "adjacent")]
+    pub pairing: Pairing,
+
+    /// Keep up to this many example sentences for each pair
+    #[arg(long, default_value_t = 0, value_name = "SENTENCES")]
+    pub concordance: usize,
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// the body of a claim marker: who checked the object out, and when, in milliseconds since the
+// epoch so that leases of a second or two are not cut short by rounding
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Lease {
+    pub worker: String,
+    pub claimed_at: u64,
+}
+
+impl Lease {
+    pub fn new(worker: &str) -> Self {
+        Lease {
+            worker: worker.to_string(),
+            claimed_at: now(),
+        }
+    }
+
+    pub fn expired(&self, duration: Duration) -> bool {
+        u128::from(now().saturating_sub(self.claimed_at)) >= duration.as_millis()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
@@ -1,124 +1,348 @@
-use std::{fs::read_to_string, time::Instant};
+use std::{fs, io::stdout, path::PathBuf, sync::Arc, time::Duration};
 
 use ascii_table::{Align, AsciiTable};
 use book_helper::Book;
-use file_helper::read_file;
+use bucket::{Bucket, Checkout};
+use error::{Error, Result};
+use file_helper::read_text;
 use folder::{merge_process, single_process};
-use s3_helper::Bucket;
+use ingestion::Ingestion;
+use memory_storage::MemoryStorage;
+use output::{write_registry, write_sentences, View};
+use rayon::ThreadPoolBuilder;
+use storage::StorageConfig;
 
 use crate::registry::Registry;
 
 mod book_helper;
+mod bucket;
+pub mod chunker;
 pub mod color;
+pub mod concordance;
 pub mod discontinuity_detector;
+pub mod error;
 mod file_helper;
 mod folder;
+pub mod hyper_ortho;
+pub mod ingestion;
 pub mod item;
+pub mod lease;
 pub mod line;
-mod ortho;
-mod registry;
+pub mod lineage;
+mod local_storage;
+pub mod memory_storage;
+pub mod ortho;
+pub mod output;
+pub mod pairing;
+pub mod registry;
 mod s3_helper;
+pub mod source;
+pub mod stats;
+pub mod storage;
+pub mod tokenizer;
+pub mod vocabulary;
 
 #[tokio::main]
-pub async fn add(file_name: String, endpoint: String, location: String) {
-    let text = read_to_string(&file_name).unwrap();
-    let bucket = Bucket::new(endpoint, location).await;
+pub async fn add(file_name: String, ingestion: Ingestion, config: StorageConfig) -> Result<()> {
+    let bucket = Bucket::new(config).await;
 
-    if bucket.bucket_does_not_exist().await {
-        bucket.create_bucket().await;
+    queue_book(&bucket, &file_name, &ingestion).await
+}
+
+async fn queue_book(bucket: &Bucket, file_name: &str, ingestion: &Ingestion) -> Result<()> {
+    let text = read_text(file_name)?;
+
+    if bucket.bucket_does_not_exist().await? {
+        bucket.create_bucket().await?;
     }
 
-    let body = read_file(&file_name);
-    bucket.save_to_bucket_top_level(&file_name, body).await;
+    let registries = ingestion
+        .chunker
+        .chunks(&text)
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk_text)| {
+            Registry::from_book(&Book::book_from_text(
+                file_name,
+                chunk_text,
+                index + 1,
+                ingestion,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // everything in a store is merged together in the end, so a book split or paired
+    // differently from what is already there is refused before anything is queued
+    if let (Some(registry), Some(queued)) = (registries.first(), bucket.any_registry().await?) {
+        queued.check_compatible(registry)?;
+    }
 
-    let chunks: Vec<_> = text.split("CHAPTER").collect();
-    let mut chunk_number = 0;
-    for chunk_text in chunks {
-        chunk_number += 1;
-        let book_chunk = Book::book_from_text(&file_name, chunk_text, chunk_number);
-        let registry = Registry::from_book(&book_chunk);
-        bucket.write_chunk(registry).await;
+    for registry in registries {
+        bucket.write_chunk(registry).await?;
     }
-    bucket.delete_from_bucket_top_level(&file_name).await;
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn get(config: StorageConfig, view: View) -> Result<()> {
+    let bucket = Bucket::new(config).await;
+    let registry = bucket.read_largest_chunk().await?.ok_or(Error::NoAnswer)?;
+
+    write_registry(&registry, &view, &mut stdout().lock())
 }
 
 #[tokio::main]
-pub async fn get(endpoint: String, location: String) {
-    let bucket = Bucket::new(endpoint, location).await;
+pub async fn answer(config: StorageConfig) -> Result<Option<Registry>> {
+    let bucket = Bucket::new(config).await;
 
-    bucket.dump_results().await;
+    bucket.read_largest_chunk().await
 }
 
 #[tokio::main]
-pub async fn delete(endpoint: String, location: String) {
-    let bucket = Bucket::new(endpoint, location).await;
+pub async fn delete(config: StorageConfig) -> Result<()> {
+    let bucket = Bucket::new(config).await;
 
     bucket.delete_largest_answer().await
 }
 
 #[tokio::main]
-pub async fn process(endpoint: String, location: String) {
-    let bucket = Bucket::new(endpoint, location).await;
-    loop {
-        if let Some(registry) = bucket.checkout_smallest_chunk().await {
-            let ans = single_process(&registry);
+pub async fn status(config: StorageConfig) -> Result<()> {
+    let bucket = Bucket::new(config).await;
 
-            let mut ascii_table = AsciiTable::default();
-            ascii_table
-                .column(0)
-                .set_header("single")
-                .set_align(Align::Left);
+    let mut ascii_table = AsciiTable::default();
+    for (index, header) in ["prefix", "objects", "bytes"].iter().enumerate() {
+        ascii_table
+            .column(index)
+            .set_header(*header)
+            .set_align(Align::Left);
+    }
 
-            let data: Vec<Vec<usize>> =
-                vec![vec![ans.number_of_pairs()], vec![ans.number_of_squares()]];
-            ascii_table.print(data);
+    let data: Vec<Vec<String>> = bucket
+        .status()
+        .await?
+        .into_iter()
+        .map(|(prefix, count, size)| vec![prefix.to_string(), count.to_string(), size.to_string()])
+        .collect();
+    ascii_table.print(data);
+    Ok(())
+}
 
-            bucket.save_answer(ans).await;
-            bucket.delete_chunk(registry).await;
+// with a square's words, also shows the sentences behind each of its edges
+#[tokio::main]
+pub async fn inspect(
+    config: StorageConfig,
+    key: String,
+    square: Option<[String; 4]>,
+) -> Result<()> {
+    let bucket = Bucket::new(config).await;
+    let registry = bucket.read_registry(&key).await?;
+
+    println!("name: {}", registry.name());
+    println!("pairs: {}", registry.number_of_pairs());
+    println!("squares: {}", registry.number_of_squares());
+    println!("orthos: {}", registry.orthos.len());
+    println!("tokenizer: {}", registry.tokenization);
+    println!("pairing: {}", registry.pairing);
+    if let Some(min_count) = registry.min_count {
+        println!("min count: {}", min_count);
+    }
+    println!("provenance:");
+    for source in &registry.provenance {
+        println!("  {}", source);
+    }
+
+    if let Some([a, b, c, d]) = &square {
+        let square = registry
+            .square(a, b, c, d)
+            .ok_or_else(|| Error::NotFound(format!("the square {} {} {} {}", a, b, c, d)))?;
+        println!("{}", square.with_words(&registry.vocabulary));
+        write_sentences(&registry, &square, &mut stdout().lock())?;
+    }
+    Ok(())
+}
+
+// lists the chunks the square a b / c d of the largest answer came from, edge by edge
+#[tokio::main]
+pub async fn explain(config: StorageConfig, words: [String; 4]) -> Result<()> {
+    let bucket = Bucket::new(config).await;
+    let registry = bucket.read_largest_chunk().await?.ok_or(Error::NoAnswer)?;
+
+    let [a, b, c, d] = &words;
+    let explanation = registry
+        .explain(a, b, c, d)
+        .ok_or_else(|| Error::NotFound(format!("the square {} {} {} {}", a, b, c, d)))?;
+    println!("square spans:");
+    for source in explanation.square {
+        println!("  {}", source);
+    }
+    for (edge, sources) in explanation.edges {
+        println!(
+            "{} {} ({} times):",
+            registry.vocabulary.word(edge.first),
+            registry.vocabulary.word(edge.second),
+            registry.count(&edge)
+        );
+        for source in sources {
+            println!("  {}", source);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn recover(config: StorageConfig, lease: Duration) -> Result<()> {
+    let bucket = Bucket::new(config).await;
+
+    for key in bucket.recover(lease).await? {
+        println!("recovered {}", key);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn process(
+    config: StorageConfig,
+    lease: Duration,
+    min_count: u64,
+    extend: usize,
+    threads: usize,
+) -> Result<()> {
+    let bucket = Bucket::new(config).await;
+    for key in bucket.recover(lease).await? {
+        println!("recovered {}", key);
+    }
+    run(&bucket, true, lease, min_count, extend, threads).await
+}
+
+// folds books entirely in memory, so nothing is read from or written to an object store.
+// the final answer is printed, or written to save_to in the same form answers are stored in
+#[tokio::main]
+pub async fn fold(
+    file_names: Vec<String>,
+    ingestion: Ingestion,
+    view: View,
+    min_count: u64,
+    extend: usize,
+    threads: usize,
+    save_to: Option<PathBuf>,
+) -> Result<()> {
+    let bucket = Bucket::new(StorageConfig::Memory(MemoryStorage::default())).await;
+    for file_name in &file_names {
+        queue_book(&bucket, file_name, &ingestion).await?;
+    }
+    // nothing else works on the in-memory store, so no lease is ever taken over
+    run(&bucket, false, FOLD_LEASE, min_count, extend, threads).await?;
+
+    let registry = bucket.read_largest_chunk().await?.ok_or(Error::NoAnswer)?;
+    match save_to {
+        Some(path) => Ok(fs::write(path, bincode::serialize(&registry)?)?),
+        None => write_registry(&registry, &view, &mut stdout().lock()),
+    }
+}
+
+const FOLD_LEASE: Duration = Duration::from_secs(3600);
+
+// how long to wait before looking for answers again while other workers hold some
+const CONTENDED_WAIT: Duration = Duration::from_millis(200);
+
+// single processes chunks, smallest first, then merges the largest and smallest answers
+// until one is left. squares are only formed from edges seen at least min_count times, and
+// grown into larger orthos by extend passes. folding is spread over threads threads, or
+// every core when threads is 0
+async fn run(
+    bucket: &Bucket,
+    report: bool,
+    lease: Duration,
+    min_count: u64,
+    extend: usize,
+    threads: usize,
+) -> Result<()> {
+    let pool = Arc::new(
+        ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| Error::Threads(e.to_string()))?,
+    );
+    loop {
+        if let Some(registry) = bucket.checkout_smallest_chunk().await? {
+            let name = registry.name.clone();
+            let pool = pool.clone();
+            let (registry, (ans, stats)) = hold(bucket, "chunks", &[&name], lease, move || {
+                let res = pool.install(|| single_process(&registry, min_count, extend));
+                (registry, res)
+            })
+            .await?;
+
+            if report {
+                println!("single {}", stats);
+            }
+
+            bucket.confirm("chunks", &[&name]).await?;
+            bucket.save_stats(&[&name], &stats).await?;
+            bucket.save_answer(ans).await?;
+            bucket.delete_chunk(registry).await?;
         } else {
-            if let Some((source_answer, target_answer)) =
-                bucket.checkout_largest_and_smallest_answer().await
-            {
-                let start = Instant::now();
-                let new_answer = merge_process(&source_answer, &target_answer);
-                let duration = start.elapsed();
-
-                println!("Time elapsed in merge is: {:?}", duration);
-
-                let mut ascii_table = AsciiTable::default();
-
-                ascii_table
-                    .column(0)
-                    .set_header(source_answer.provenance.len().to_string())
-                    .set_align(Align::Left);
-                ascii_table
-                    .column(1)
-                    .set_header(target_answer.provenance.len().to_string())
-                    .set_align(Align::Left);
-                ascii_table
-                    .column(2)
-                    .set_header(new_answer.provenance.len().to_string())
-                    .set_align(Align::Left);
-
-                let data: Vec<Vec<usize>> = vec![
-                    vec![
-                        source_answer.number_of_pairs(),
-                        target_answer.number_of_pairs(),
-                        new_answer.number_of_pairs(),
-                    ],
-                    vec![
-                        source_answer.number_of_squares(),
-                        target_answer.number_of_squares(),
-                        new_answer.number_of_squares(),
-                    ],
-                ];
-                ascii_table.print(data);
-                bucket.save_answer(new_answer).await;
-                bucket.delete_answer(source_answer).await;
-                bucket.delete_answer(target_answer).await;
-            } else {
-                break;
+            match bucket.checkout_largest_and_smallest_answer().await? {
+                Checkout::Pair(pair) => {
+                    let held = [pair.0.name.clone(), pair.1.name.clone()];
+                    let names = held.each_ref().map(String::as_str);
+                    let pool = pool.clone();
+                    let (pair, merged) = hold(bucket, "answers", &names, lease, move || {
+                        let res =
+                            pool.install(|| merge_process(&pair.0, &pair.1, min_count, extend));
+                        (pair, res)
+                    })
+                    .await?;
+                    let (source_answer, target_answer) = *pair;
+                    let (new_answer, stats) = match merged {
+                        Ok(merged) => merged,
+                        // put both back so that the queues stay as they were
+                        Err(e) => {
+                            bucket.return_answers(&names).await?;
+                            return Err(e);
+                        }
+                    };
+
+                    if report {
+                        println!("merge {}", stats);
+                    }
+                    bucket.confirm("answers", &names).await?;
+                    bucket.save_stats(&names, &stats).await?;
+                    bucket.save_answer(new_answer).await?;
+                    bucket.delete_answer(source_answer).await?;
+                    bucket.delete_answer(target_answer).await?;
+                }
+                // another worker may have died holding what is left, so take back anything
+                // whose lease ran out before looking again
+                Checkout::Contended => {
+                    tokio::time::sleep(CONTENDED_WAIT).await;
+                    for key in bucket.recover(lease).await? {
+                        println!("recovered {}", key);
+                    }
+                }
+                Checkout::Done => break,
             }
         }
     }
+    Ok(())
+}
+
+// runs work off the async threads, renewing the claims on names every third of a lease
+// until it is done so that no other worker recovers them in the meantime
+async fn hold<T: Send + 'static>(
+    bucket: &Bucket,
+    prefix: &str,
+    names: &[&str],
+    lease: Duration,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T> {
+    let mut work = tokio::task::spawn_blocking(work);
+    // interval panics on a zero period, which a zero lease would give
+    let mut heartbeat = tokio::time::interval((lease / 3).max(Duration::from_millis(1)));
+    heartbeat.tick().await;
+    loop {
+        tokio::select! {
+            done = &mut work => return Ok(done.expect("folding panicked")),
+            _ = heartbeat.tick() => bucket.renew(prefix, names, lease).await?,
+        }
+    }
 }
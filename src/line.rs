@@ -1,12 +1,23 @@
 use serde::{Deserialize, Serialize};
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Eq, Hash)]
+
+use crate::vocabulary::WordId;
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize, Eq, Hash)]
 pub struct Line {
-    pub first: String,
-    pub second: String,
+    pub first: WordId,
+    pub second: WordId,
+}
+
+impl Line {
+    pub(crate) fn remap(&self, mapping: &[WordId]) -> Self {
+        Line {
+            first: mapping[self.first as usize],
+            second: mapping[self.second as usize],
+        }
+    }
 }
 
-impl Into<(String, String)> for Line {
-    fn into(self) -> (String, String) {
-        (self.first, self.second)
+impl From<Line> for (WordId, WordId) {
+    fn from(line: Line) -> Self {
+        (line.first, line.second)
     }
 }
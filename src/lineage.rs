@@ -0,0 +1,66 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::line::Line;
+use crate::ortho::Ortho;
+use crate::source::Source;
+use crate::vocabulary::WordId;
+
+// the chunks each pair and square came from, as indexes into the registry's provenance
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct Lineage {
+    pairs: HashMap<Line, BTreeSet<u32>>,
+    squares: HashMap<Ortho, BTreeSet<u32>>,
+}
+
+impl Lineage {
+    // every pair of a chunk comes from the chunk itself, the only entry of its provenance
+    pub(crate) fn of_chunk<'a>(pairs: impl Iterator<Item = &'a Line>) -> Self {
+        Lineage {
+            pairs: pairs.map(|line| (*line, BTreeSet::from([0]))).collect(),
+            squares: HashMap::default(),
+        }
+    }
+
+    pub fn pair(&self, line: &Line) -> impl Iterator<Item = u32> + '_ {
+        self.pairs.get(line).into_iter().flatten().copied()
+    }
+
+    pub fn square(&self, square: &Ortho) -> impl Iterator<Item = u32> + '_ {
+        self.squares.get(square).into_iter().flatten().copied()
+    }
+
+    // a square spans every chunk that any of its edges came from
+    pub(crate) fn record_square(&mut self, square: Ortho) {
+        let sources: BTreeSet<u32> = square
+            .edges()
+            .iter()
+            .flat_map(|edge| self.pair(edge))
+            .collect();
+        self.squares.entry(square).or_default().extend(sources);
+    }
+
+    // takes in other's lineage, whose words are mapped to ours and whose provenance was
+    // appended after the first offset entries of ours
+    pub(crate) fn absorb(&mut self, other: &Self, mapping: &[WordId], offset: u32) {
+        for (line, sources) in &other.pairs {
+            self.pairs
+                .entry(line.remap(mapping))
+                .or_default()
+                .extend(sources.iter().map(|source| source + offset));
+        }
+        for (square, sources) in &other.squares {
+            self.squares
+                .entry(square.remap(mapping))
+                .or_default()
+                .extend(sources.iter().map(|source| source + offset));
+        }
+    }
+}
+
+// the chunks behind one square as a whole, and behind each of its edges
+pub struct Explanation<'a> {
+    pub square: Vec<&'a Source>,
+    pub edges: Vec<(Line, Vec<&'a Source>)>,
+}
@@ -0,0 +1,149 @@
+use std::{
+    io::{self, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::error::{Error, Result};
+use crate::storage::{Storage, StoredObject};
+
+// each queue prefix is a directory under root, e.g. root/chunks/example-1.txt
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> Self {
+        LocalStorage { root }
+    }
+
+    // keys are relative to root. joining an absolute key would replace root, and .. would
+    // climb out of it
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        if relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            Ok(self.root.join(relative))
+        } else {
+            Err(Error::storage(format!(
+                "{} is not a key inside the store",
+                key
+            )))
+        }
+    }
+
+    async fn create_parent(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| failure(parent, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for LocalStorage {
+    async fn exists(&self) -> Result<bool> {
+        fs::try_exists(&self.root)
+            .await
+            .map_err(|e| failure(&self.root, e))
+    }
+
+    async fn create(&self) -> Result<()> {
+        fs::create_dir_all(&self.root)
+            .await
+            .map_err(|e| failure(&self.root, e))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let mut res = vec![];
+        let directory = self.path(prefix)?;
+        let mut entries = match fs::read_dir(&directory).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(res),
+            Err(e) => return Err(failure(&directory, e)),
+        };
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| failure(&directory, e))?
+        {
+            let metadata = entry
+                .metadata()
+                .await
+                .map_err(|e| failure(&entry.path(), e))?;
+            if metadata.is_file() {
+                res.push(StoredObject {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    size: metadata.len(),
+                });
+            }
+        }
+        Ok(res)
+    }
+
+    async fn read(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path(key)?;
+        fs::read(&path).await.map_err(|e| missing(key, &path, e))
+    }
+
+    async fn write(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        let path = self.path(key)?;
+        self.create_parent(&path).await?;
+        fs::write(&path, body).await.map_err(|e| failure(&path, e))
+    }
+
+    async fn write_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool> {
+        let path = self.path(key)?;
+        self.create_parent(&path).await?;
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+        {
+            Ok(mut file) => {
+                file.write_all(&body).await.map_err(|e| failure(&path, e))?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(failure(&path, e)),
+        }
+    }
+
+    async fn has(&self, key: &str) -> Result<bool> {
+        let path = self.path(key)?;
+        fs::try_exists(&path).await.map_err(|e| failure(&path, e))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path(key)?;
+        fs::remove_file(&path)
+            .await
+            .map_err(|e| missing(key, &path, e))
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let source = self.path(from)?;
+        let destination = self.path(to)?;
+        self.create_parent(&destination).await?;
+        fs::rename(&source, &destination)
+            .await
+            .map_err(|e| missing(from, &source, e))
+    }
+}
+
+fn failure(path: &Path, e: io::Error) -> Error {
+    Error::storage(format!("{}: {}", path.display(), e))
+}
+
+fn missing(key: &str, path: &Path, e: io::Error) -> Error {
+    if e.kind() == ErrorKind::NotFound {
+        Error::NotFound(key.to_string())
+    } else {
+        failure(path, e)
+    }
+}
@@ -1,40 +1,222 @@
-use clap::{command, Parser};
-use parachute::{add, delete, get, process};
+use std::{io, path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
+use parachute::{
+    add, delete, error::Error, explain, fold, get, ingestion::Ingestion, inspect, output::View,
+    process, recover, status, storage::StorageConfig,
+};
+
+#[derive(ValueEnum, Clone, Debug)]
+enum Backend {
+    S3,
+    Local,
+}
 
 #[derive(Parser, Debug)]
 #[command(about, long_about = None)]
 struct Args {
+    /// Bucket name for s3, or root directory for local. Not needed by fold
     #[arg(short, long, value_name = "S3 PATH")]
-    location: String,
+    location: Option<String>,
 
     #[arg(short, long)]
-    endpoint: String,
+    endpoint: Option<String>,
 
-    #[arg(short, long, value_name = "FILENAME")]
-    add: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = Backend::S3)]
+    backend: Backend,
 
-    #[arg(short, long)]
-    get: bool,
+    #[command(subcommand)]
+    command: Command,
+}
 
-    #[arg(short, long)]
-    delete: bool,
+// how long a worker may hold what it checked out, shared by process and recover
+#[derive(clap::Args, Debug)]
+struct Lease {
+    /// Seconds after which a checked-out object is considered abandoned
+    #[arg(long, default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    lease_seconds: u64,
+}
+
+// how far squares are grown and on how many threads, shared by process and fold
+#[derive(clap::Args, Debug)]
+struct Growth {
+    /// Extension passes that grow squares into larger orthos, one row, column or dimension each
+    #[arg(long, default_value_t = 0)]
+    extend: usize,
+
+    /// Threads to fold on, or 0 for one per core
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Split a text file into chunks and queue them for processing
+    Add {
+        #[arg(value_name = "FILENAME")]
+        file_name: String,
+
+        #[command(flatten)]
+        ingestion: Ingestion,
+    },
+    /// Fold queued chunks and merge answers until one answer is left
+    Process {
+        #[command(flatten)]
+        lease: Lease,
+
+        /// Times an edge must have been seen before squares are formed from it
+        #[arg(long, default_value_t = 1)]
+        min_count: u64,
+
+        #[command(flatten)]
+        growth: Growth,
+    },
+    /// Print the largest answer
+    Get {
+        #[command(flatten)]
+        view: View,
+    },
+    /// Delete the largest answer
+    Delete,
+    /// Return objects stranded by dead workers to their queues
+    Recover {
+        #[command(flatten)]
+        lease: Lease,
+    },
+    /// Show how many objects, and how many bytes, sit in each queue
+    Status,
+    /// Fold local text files in memory without touching any store, and print the answer
+    Fold {
+        #[arg(value_name = "FILENAME", required = true)]
+        file_names: Vec<String>,
+
+        #[command(flatten)]
+        ingestion: Ingestion,
+
+        #[command(flatten)]
+        view: View,
+
+        /// Times an edge must have been seen before squares are formed from it while folding.
+        /// --min-count only filters what is printed
+        #[arg(long, default_value_t = 1)]
+        fold_min_count: u64,
+
+        #[command(flatten)]
+        growth: Growth,
+
+        /// Write the answer to this file, in the form answers are stored in, instead of printing it
+        #[arg(long, value_name = "PATH")]
+        save: Option<PathBuf>,
+    },
+    /// List the chunks behind each edge of a square of the largest answer
+    Explain {
+        /// The square's words, top row first: a b c d for a-b over c-d
+        #[arg(num_args = 4, value_names = ["A", "B", "C", "D"])]
+        words: Vec<String>,
+    },
+    /// Show the pairs, squares and provenance of one object
+    Inspect {
+        /// Key of the object, e.g. answers/example-1.txt
+        key: String,
+
+        /// Also show the example sentences behind each edge of this square
+        #[arg(long, num_args = 4, value_names = ["A", "B", "C", "D"])]
+        square: Option<Vec<String>>,
+    },
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
 
-    if args.get {
-        get(args.endpoint, args.location)
-    } else if args.add.is_some() {
-        add(
-            args.add.unwrap(),
-            args.endpoint.clone(),
-            args.location.clone(),
-        );
-    } else if args.delete {
-        delete(args.endpoint, args.location);
+    let result = match args.command {
+        Command::Fold {
+            file_names,
+            ingestion,
+            view,
+            fold_min_count,
+            growth,
+            save,
+        } => fold(
+            file_names,
+            ingestion,
+            view,
+            fold_min_count,
+            growth.extend,
+            growth.threads,
+            save,
+        ),
+        command => run(
+            command,
+            storage_config(args.backend, args.location, args.endpoint),
+        ),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // whatever read the output, e.g. head, has all it wanted
+        Err(Error::Output(e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
     }
-        else {
-        process(args.endpoint, args.location);
+}
+
+fn storage_config(
+    backend: Backend,
+    location: Option<String>,
+    endpoint: Option<String>,
+) -> StorageConfig {
+    let Some(location) = location else {
+        missing_argument("--location is required");
+    };
+    match backend {
+        Backend::S3 => StorageConfig::S3 {
+            endpoint: endpoint.unwrap_or_else(|| missing_argument("--endpoint is required for s3")),
+            location,
+        },
+        Backend::Local => StorageConfig::Local {
+            root: PathBuf::from(location),
+        },
+    }
+}
+
+fn missing_argument(message: &str) -> ! {
+    Args::command()
+        .error(ErrorKind::MissingRequiredArgument, message)
+        .exit()
+}
+
+fn run(command: Command, config: StorageConfig) -> parachute::error::Result<()> {
+    match command {
+        Command::Add {
+            file_name,
+            ingestion,
+        } => add(file_name, ingestion, config),
+        Command::Process {
+            lease,
+            min_count,
+            growth,
+        } => process(
+            config,
+            Duration::from_secs(lease.lease_seconds),
+            min_count,
+            growth.extend,
+            growth.threads,
+        ),
+        Command::Get { view } => get(config, view),
+        Command::Delete => delete(config),
+        Command::Recover { lease } => recover(config, Duration::from_secs(lease.lease_seconds)),
+        Command::Status => status(config),
+        Command::Inspect { key, square } => inspect(
+            config,
+            key,
+            square.map(|words| words.try_into().expect("clap takes exactly four words")),
+        ),
+        Command::Explain { words } => explain(
+            config,
+            words.try_into().expect("clap takes exactly four words"),
+        ),
+        Command::Fold { .. } => unreachable!("fold does not use a store"),
     }
 }
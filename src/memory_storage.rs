@@ -0,0 +1,86 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    sync::{Arc, Mutex},
+};
+
+use crate::error::{Error, Result};
+use crate::storage::{Storage, StoredObject};
+
+// clones share the same objects, so one store can be handed to add, process and get in turn
+#[derive(Clone, Default, Debug)]
+pub struct MemoryStorage {
+    objects: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+}
+
+impl Storage for MemoryStorage {
+    async fn exists(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn create(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let directory = prefix.to_owned() + "/";
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(key, body)| {
+                let name = key.strip_prefix(&directory)?;
+                (!name.contains('/')).then(|| StoredObject {
+                    name: name.to_string(),
+                    size: body.len() as u64,
+                })
+            })
+            .collect())
+    }
+
+    async fn read(&self, key: &str) -> Result<Vec<u8>> {
+        self.objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(key.to_string()))
+    }
+
+    async fn write(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        self.objects.lock().unwrap().insert(key.to_string(), body);
+        Ok(())
+    }
+
+    async fn write_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool> {
+        match self.objects.lock().unwrap().entry(key.to_string()) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(body);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn has(&self, key: &str) -> Result<bool> {
+        Ok(self.objects.lock().unwrap().contains_key(key))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.objects
+            .lock()
+            .unwrap()
+            .remove(key)
+            .map(|_| ())
+            .ok_or_else(|| Error::NotFound(key.to_string()))
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let mut objects = self.objects.lock().unwrap();
+        let body = objects
+            .remove(from)
+            .ok_or_else(|| Error::NotFound(from.to_string()))?;
+        objects.insert(to.to_string(), body);
+        Ok(())
+    }
+}
@@ -1,15 +1,116 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Hash, Eq)]
+use crate::line::Line;
+use crate::vocabulary::{Vocabulary, WordId};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Hash, Eq)]
 pub struct Ortho {
-    a: String,
-    b: String,
-    c: String,
-    d: String,
+    a: WordId,
+    b: WordId,
+    c: WordId,
+    d: WordId,
 }
 
+// a b     a c
+// c d and b d are the same square mirrored across a-d, so only the one with the word of b
+// sorting before the word of c is kept. words, unlike ids, do not depend on the order they
+// were first seen in
 impl Ortho {
-    pub fn new(a: String, b: String, c: String, d: String) -> Self {
+    pub fn new(a: WordId, b: WordId, c: WordId, d: WordId) -> Self {
         Ortho { a, b, c, d }
     }
+
+    pub fn canonical(self, vocabulary: &Vocabulary) -> Self {
+        if vocabulary.word(self.b) <= vocabulary.word(self.c) {
+            self
+        } else {
+            Ortho {
+                b: self.c,
+                c: self.b,
+                ..self
+            }
+        }
+    }
+
+    pub(crate) fn remap(&self, mapping: &[WordId]) -> Self {
+        Ortho::new(
+            mapping[self.a as usize],
+            mapping[self.b as usize],
+            mapping[self.c as usize],
+            mapping[self.d as usize],
+        )
+    }
+
+    pub fn words<'a>(&self, vocabulary: &'a Vocabulary) -> [&'a str; 4] {
+        [
+            vocabulary.word(self.a),
+            vocabulary.word(self.b),
+            vocabulary.word(self.c),
+            vocabulary.word(self.d),
+        ]
+    }
+
+    pub fn a(&self) -> WordId {
+        self.a
+    }
+
+    pub fn b(&self) -> WordId {
+        self.b
+    }
+
+    pub fn c(&self) -> WordId {
+        self.c
+    }
+
+    pub fn d(&self) -> WordId {
+        self.d
+    }
+
+    // a-b, a-c, b-d and c-d
+    pub fn edges(&self) -> [Line; 4] {
+        [
+            (self.a, self.b),
+            (self.a, self.c),
+            (self.b, self.d),
+            (self.c, self.d),
+        ]
+        .map(|(first, second)| Line { first, second })
+    }
+
+    // the square with its ids resolved, for printing
+    pub fn with_words<'a>(&self, vocabulary: &'a Vocabulary) -> OrthoWords<'a> {
+        OrthoWords(self.words(vocabulary))
+    }
+}
+
+pub struct OrthoWords<'a>([&'a str; 4]);
+
+impl<'a> From<[&'a str; 4]> for OrthoWords<'a> {
+    fn from(words: [&'a str; 4]) -> Self {
+        OrthoWords(words)
+    }
+}
+
+// a b
+// c d
+// padded so that b sits above d
+fn write_grid(f: &mut fmt::Formatter<'_>, [a, b, c, d]: [&str; 4]) -> fmt::Result {
+    let width = a.chars().count().max(c.chars().count());
+    writeln!(f, "{:<width$} {}", a, b)?;
+    write!(f, "{:<width$} {}", c, d)
+}
+
+impl fmt::Display for Ortho {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = [self.a, self.b, self.c, self.d].map(|id| id.to_string());
+        write_grid(f, [&ids[0], &ids[1], &ids[2], &ids[3]])
+    }
+}
+
+impl fmt::Display for OrthoWords<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_grid(f, self.0)
+    }
 }
@@ -0,0 +1,301 @@
+use std::io::Write;
+
+use ascii_table::{Align, AsciiTable};
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+use crate::hyper_ortho::{shape_name, HyperOrtho};
+use crate::ortho::{Ortho, OrthoWords};
+use crate::registry::Registry;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    Csv,
+    /// A table for reading in a terminal
+    Grid,
+    /// Each square or ortho laid out as a grid of words
+    Tiles,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+    Squares,
+    /// Orthos larger than a square, grown with --extend
+    Orthos,
+    Pairs,
+    Provenance,
+}
+
+// what part of a registry to print, and how
+#[derive(clap::Args, Clone, Debug)]
+pub struct View {
+    #[arg(short, long, value_enum, default_value_t = Format::Grid)]
+    pub format: Format,
+
+    /// Which part of the answer to print
+    #[arg(short, long, value_enum, default_value_t = Section::Squares)]
+    pub section: Section,
+
+    /// Print only this page of the sorted results, counting from 1
+    #[arg(long)]
+    pub page: Option<usize>,
+
+    #[arg(long, default_value_t = 20, requires = "page")]
+    pub page_size: usize,
+
+    /// Leave out squares and pairs with an edge seen fewer times than this
+    #[arg(long, default_value_t = 1)]
+    pub min_count: u64,
+
+    /// Print the example sentences behind each edge under each square, in the tiles format
+    #[arg(long)]
+    pub sentences: bool,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View {
+            format: Format::Grid,
+            section: Section::Squares,
+            page: None,
+            page_size: 20,
+            min_count: 1,
+            sentences: false,
+        }
+    }
+}
+
+pub fn write_registry(registry: &Registry, view: &View, out: &mut impl Write) -> Result<()> {
+    let section = view.section;
+    let (headers, rows) = table(registry, section, view.min_count);
+    let rows = page(view, rows);
+    // orthos are written from the orthos themselves wherever the output keeps their structure
+    let orthos = match section {
+        Section::Orthos => page(view, orthos(registry, view.min_count)),
+        _ => vec![],
+    };
+    let records = || match section {
+        Section::Orthos => orthos
+            .iter()
+            .map(|ortho| ortho_record(registry, ortho))
+            .collect_vec(),
+        _ => rows.iter().map(|row| record(&headers, row)).collect_vec(),
+    };
+    match view.format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, &records()).map_err(std::io::Error::from)?;
+            writeln!(out)?;
+        }
+        Format::Jsonl => {
+            for record in records() {
+                serde_json::to_writer(&mut *out, &record).map_err(std::io::Error::from)?;
+                writeln!(out)?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "{}", headers.iter().map(|h| csv_field(h)).join(","))?;
+            for row in &rows {
+                writeln!(out, "{}", row.iter().map(|f| csv_field(f)).join(","))?;
+            }
+        }
+        Format::Grid => {
+            let mut ascii_table = AsciiTable::default();
+            for (index, header) in headers.iter().enumerate() {
+                ascii_table
+                    .column(index)
+                    .set_header(*header)
+                    .set_align(Align::Left);
+            }
+            write!(out, "{}", ascii_table.format(rows))?;
+        }
+        Format::Tiles if section == Section::Orthos => {
+            for (index, ortho) in orthos.iter().enumerate() {
+                if index > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", ortho.with_words(&registry.vocabulary))?;
+            }
+        }
+        Format::Tiles => {
+            for (index, row) in rows.iter().enumerate() {
+                if index > 0 {
+                    writeln!(out)?;
+                }
+                match row.as_slice() {
+                    [a, b, c, d] if section == Section::Squares => {
+                        writeln!(
+                            out,
+                            "{}",
+                            OrthoWords::from([a, b, c, d].map(String::as_str))
+                        )?;
+                        if view.sentences {
+                            if let Some(square) = registry.square(a, b, c, d) {
+                                write_sentences(registry, &square, out)?;
+                            }
+                        }
+                    }
+                    _ => writeln!(out, "{}", row.join(" "))?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// each edge of the square followed by the sentences it was seen in, when they were kept
+pub(crate) fn write_sentences(
+    registry: &Registry,
+    square: &Ortho,
+    out: &mut impl Write,
+) -> Result<()> {
+    for edge in square.edges() {
+        writeln!(
+            out,
+            "  {} {}:",
+            registry.vocabulary.word(edge.first),
+            registry.vocabulary.word(edge.second)
+        )?;
+        for sample in registry.concordance.samples(&edge) {
+            writeln!(
+                out,
+                "    {} ({}, chunk {})",
+                sample.sentence, sample.file_name, sample.chunk_number
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// column names and rows of words, sorted so output is stable between runs. squares and
+// orthos and pairs with an edge seen fewer than min_count times are left out
+fn table(
+    registry: &Registry,
+    section: Section,
+    min_count: u64,
+) -> (Vec<&'static str>, Vec<Vec<String>>) {
+    let vocabulary = &registry.vocabulary;
+    let word = |id| vocabulary.word(id).to_string();
+    match section {
+        Section::Squares => (
+            vec!["a", "b", "c", "d"],
+            registry
+                .squares
+                .iter()
+                .filter(|square| registry.support(square) >= min_count)
+                .map(|square| {
+                    vec![
+                        word(square.a()),
+                        word(square.b()),
+                        word(square.c()),
+                        word(square.d()),
+                    ]
+                })
+                .sorted()
+                .collect(),
+        ),
+        Section::Orthos => (
+            vec!["shape", "words"],
+            orthos(registry, min_count)
+                .into_iter()
+                .map(|ortho| {
+                    vec![
+                        shape_name(ortho.shape()),
+                        ortho.words().iter().map(|w| vocabulary.word(*w)).join(" "),
+                    ]
+                })
+                .collect(),
+        ),
+        Section::Pairs => (
+            vec!["first", "second", "count"],
+            registry
+                .pairs
+                .iter()
+                .filter(|(_, count)| **count >= min_count)
+                .sorted_by_key(|(line, _)| {
+                    (vocabulary.word(line.first), vocabulary.word(line.second))
+                })
+                .map(|(line, count)| vec![word(line.first), word(line.second), count.to_string()])
+                .collect(),
+        ),
+        Section::Provenance => (
+            vec!["source", "chunker"],
+            registry
+                .provenance
+                .iter()
+                .map(|source| vec![source.name.clone(), source.chunker.clone()])
+                .sorted()
+                .collect(),
+        ),
+    }
+}
+
+// the orthos with every edge seen at least min_count times, smallest shape first and then by
+// their words
+fn orthos(registry: &Registry, min_count: u64) -> Vec<&HyperOrtho> {
+    registry
+        .orthos
+        .iter()
+        .filter(|ortho| registry.ortho_support(ortho) >= min_count)
+        .sorted_by_cached_key(|ortho| {
+            (
+                ortho.shape().to_vec(),
+                ortho
+                    .words()
+                    .iter()
+                    .map(|w| registry.vocabulary.word(*w))
+                    .collect_vec(),
+            )
+        })
+        .collect()
+}
+
+fn page<T>(view: &View, items: Vec<T>) -> Vec<T> {
+    match view.page {
+        Some(number) => items
+            .into_iter()
+            .skip(number.saturating_sub(1) * view.page_size)
+            .take(view.page_size)
+            .collect(),
+        None => items,
+    }
+}
+
+// the shape as a list of lengths and the words as a list in row-major order
+fn ortho_record(registry: &Registry, ortho: &HyperOrtho) -> Map<String, Value> {
+    let words = ortho
+        .words()
+        .iter()
+        .map(|w| Value::from(registry.vocabulary.word(*w)))
+        .collect_vec();
+    Map::from_iter([
+        ("shape".to_string(), Value::from(ortho.shape().to_vec())),
+        ("words".to_string(), Value::from(words)),
+    ])
+}
+
+fn record(headers: &[&str], row: &[String]) -> Map<String, Value> {
+    headers
+        .iter()
+        .zip(row)
+        .map(|(header, field)| {
+            let value = match *header {
+                "count" => Value::from(field.parse::<u64>().unwrap_or_default()),
+                _ => Value::from(field.as_str()),
+            };
+            (header.to_string(), value)
+        })
+        .collect()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::line::Line;
+use crate::vocabulary::WordId;
+
+// which pairs of words in a sentence become lines. stored with a registry, since squares
+// folded over one kind of edge say nothing about another
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Pairing {
+    // each word and the word after it
+    #[default]
+    Adjacent,
+    // each word and the word k places after it
+    Distance(usize),
+    // each word and every word up to k places after it
+    Window(usize),
+}
+
+impl Pairing {
+    pub(crate) fn pairs(&self, words: &[WordId]) -> Vec<Line> {
+        let (nearest, furthest) = match *self {
+            Pairing::Adjacent => (1, 1),
+            Pairing::Distance(k) => (k, k),
+            Pairing::Window(k) => (1, k),
+        };
+        let mut res = vec![];
+        for (index, &first) in words.iter().enumerate() {
+            for &second in words
+                .iter()
+                .skip(index + nearest)
+                .take(furthest + 1 - nearest)
+            {
+                res.push(Line { first, second });
+            }
+        }
+        res
+    }
+}
+
+impl fmt::Display for Pairing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pairing::Adjacent => write!(f, "adjacent"),
+            Pairing::Distance(k) => write!(f, "distance:{}", k),
+            Pairing::Window(k) => write!(f, "window:{}", k),
+        }
+    }
+}
+
+impl FromStr for Pairing {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let (kind, argument) = spec.split_once(':').unwrap_or((spec, ""));
+        let k = || match argument.parse::<usize>() {
+            Ok(k) if k > 0 => Ok(k),
+            _ => Err(format!("{} needs a positive number, e.g. {}:2", kind, kind)),
+        };
+        // a distance or window of one is adjacent, and is recorded as such so it merges
+        // with adjacent registries
+        match kind {
+            "adjacent" if argument.is_empty() => Ok(Pairing::Adjacent),
+            "distance" | "window" if k()? == 1 => Ok(Pairing::Adjacent),
+            "distance" => Ok(Pairing::Distance(k()?)),
+            "window" => Ok(Pairing::Window(k()?)),
+            _ => Err(format!(
+                "unknown pairing {}, expected adjacent, distance:K or window:K",
+                spec
+            )),
+        }
+    }
+}
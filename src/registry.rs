@@ -1,16 +1,100 @@
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::{book_helper::Book, line::Line, ortho::Ortho};
+use crate::{
+    book_helper::Book,
+    concordance::Concordance,
+    error::{Error, Result},
+    hyper_ortho::HyperOrtho,
+    line::Line,
+    lineage::{Explanation, Lineage},
+    ortho::Ortho,
+    pairing::Pairing,
+    source::Source,
+    tokenizer::Tokenization,
+    vocabulary::{Vocabulary, WordId},
+};
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(from = "Stored")]
 pub struct Registry {
     pub squares: HashSet<Ortho>,
-    pub pairs: HashSet<Line>,
+    // orthos larger than a square, grown from squares by extension passes
+    pub orthos: HashSet<HyperOrtho>,
+    // how many times each pair was seen across every chunk folded in
+    pub pairs: HashMap<Line, u64>,
     pub name: String,
-    pub provenance: Vec<String>,
+    pub provenance: Vec<Source>,
+    pub vocabulary: Vocabulary,
+    pub tokenization: Tokenization,
+    pub pairing: Pairing,
+    // the min_count squares were formed with, which decides which squares each side of a merge
+    // already holds. none for a chunk that has not been folded yet
+    pub min_count: Option<u64>,
+    pub lineage: Lineage,
+    pub concordance: Concordance,
+    // adjacency indexes over pairs, kept so neighbour lookups do not scan every pair. they are
+    // rebuilt on load rather than stored, so stored objects hold each pair once
+    #[serde(skip)]
+    successors: HashMap<WordId, HashSet<WordId>>,
+    #[serde(skip)]
+    predecessors: HashMap<WordId, HashSet<WordId>>,
+}
+
+// a registry as it is stored, in the same field order but without the indexes
+#[derive(Deserialize)]
+struct Stored {
+    squares: HashSet<Ortho>,
+    orthos: HashSet<HyperOrtho>,
+    pairs: HashMap<Line, u64>,
+    name: String,
+    provenance: Vec<Source>,
+    vocabulary: Vocabulary,
+    tokenization: Tokenization,
+    pairing: Pairing,
+    min_count: Option<u64>,
+    lineage: Lineage,
+    concordance: Concordance,
+}
+
+impl From<Stored> for Registry {
+    fn from(stored: Stored) -> Self {
+        Registry {
+            squares: stored.squares,
+            orthos: stored.orthos,
+            pairs: stored.pairs,
+            name: stored.name,
+            provenance: stored.provenance,
+            vocabulary: stored.vocabulary,
+            tokenization: stored.tokenization,
+            pairing: stored.pairing,
+            min_count: stored.min_count,
+            lineage: stored.lineage,
+            concordance: stored.concordance,
+            successors: HashMap::default(),
+            predecessors: HashMap::default(),
+        }
+        .indexed()
+    }
 }
 impl Registry {
+    // rebuilds the adjacency indexes from pairs
+    fn indexed(mut self) -> Self {
+        self.successors.clear();
+        self.predecessors.clear();
+        for line in self.pairs.keys() {
+            self.successors
+                .entry(line.first)
+                .or_default()
+                .insert(line.second);
+            self.predecessors
+                .entry(line.second)
+                .or_default()
+                .insert(line.first);
+        }
+        self
+    }
+
     pub(crate) fn number_of_pairs(&self) -> usize {
         self.pairs.len()
     }
@@ -19,73 +103,82 @@ impl Registry {
         self.squares.len()
     }
 
-    pub(crate) fn get_lines(&self) -> Vec<&Line> {
-        self.pairs.iter().collect_vec()
-    }
-
-    pub fn forward(&self, from: String) -> HashSet<String> {
-        self.pairs
-            .iter()
-            .filter(|l| l.first == from)
-            .map(|l| l.second.clone())
-            .collect()
-    }
-
-    pub fn backward(&self, to: String) -> HashSet<String> {
-        self.pairs
-            .iter()
-            .filter(|l| l.second == to)
-            .map(|l| l.first.clone())
-            .collect()
-    }
-
-    pub(crate) fn left_of(&self, item: &Line) -> Vec<&Line> {
-        // for line-line-line relationships, this is as simple as:
-        // left: a-b
-        // center: a-c
-        // right: c-d
-        // a-b
-        // |
-        // c-d
-        self.lines_starting_with(&item.first)
+    pub fn forward(&self, from: WordId) -> impl Iterator<Item = &WordId> {
+        self.successors.get(&from).into_iter().flatten()
     }
 
-    pub(crate) fn right_of(&self, item: &Line) -> Vec<&Line> {
-        self.lines_starting_with(&item.second)
+    pub fn backward(&self, to: WordId) -> impl Iterator<Item = &WordId> {
+        self.predecessors.get(&to).into_iter().flatten()
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    pub(crate) fn minus(&self, target_answer: &Self) -> Self {
-        let self_prov: HashSet<_> = HashSet::from_iter(self.provenance.clone());
-        let other_prov: HashSet<_> = HashSet::from_iter(target_answer.provenance.clone());
-        let new_provenance: Vec<String> = self_prov.difference(&other_prov).cloned().collect_vec();
-        Registry {
-            squares: self
-                .squares
-                .difference(&target_answer.squares)
-                .cloned()
-                .collect(),
-            pairs: self
-                .pairs
-                .difference(&target_answer.pairs)
-                .cloned()
-                .collect(),
-            name: self.name.clone(),
-            provenance: new_provenance,
+    // registries can only be merged when their words were split and paired the same way, and
+    // their squares formed from edges seen as often
+    pub(crate) fn check_compatible(&self, other: &Self) -> Result<()> {
+        if self.tokenization != other.tokenization {
+            return Err(Error::Incompatible(format!(
+                "{} was tokenized as {} but {} as {}",
+                self.name, self.tokenization, other.name, other.tokenization
+            )));
+        }
+        if self.pairing != other.pairing {
+            return Err(Error::Incompatible(format!(
+                "{} was paired as {} but {} as {}",
+                self.name, self.pairing, other.name, other.pairing
+            )));
         }
+        if let (Some(ours), Some(theirs)) = (self.min_count, other.min_count) {
+            if ours != theirs {
+                return Err(Error::Incompatible(format!(
+                    "{} was folded with a min count of {} but {} with {}",
+                    self.name, ours, other.name, theirs
+                )));
+            }
+        }
+        Ok(())
     }
 
-    pub(crate) fn union(&self, target_answer: &Self) -> Self {
-        Registry {
-            squares: self
-                .squares
-                .union(&target_answer.squares)
+    // an answer can only be merged by a worker forming squares from edges seen as often as
+    // when it was folded
+    pub(crate) fn check_folded_with(&self, min_count: u64) -> Result<()> {
+        match self.min_count {
+            Some(folded) if folded != min_count => Err(Error::Incompatible(format!(
+                "{} was folded with a min count of {} but this worker uses {}",
+                self.name, folded, min_count
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn union(&self, target_answer: &Self) -> Result<Self> {
+        self.check_compatible(target_answer)?;
+        let mut vocabulary = self.vocabulary.clone();
+        let mapping = vocabulary.absorb(&target_answer.vocabulary);
+        let (squares, pairs) = target_answer.remapped(&mapping);
+        let mut counts = self.pairs.clone();
+        for (line, count) in pairs {
+            *counts.entry(line).or_default() += count;
+        }
+        let mut lineage = self.lineage.clone();
+        lineage.absorb(
+            &target_answer.lineage,
+            &mapping,
+            self.provenance.len() as u32,
+        );
+        let mut concordance = self.concordance.clone();
+        concordance.absorb(&target_answer.concordance, &mapping);
+        Ok(Registry {
+            squares: self.squares.union(&squares).cloned().collect(),
+            orthos: self
+                .orthos
+                .iter()
                 .cloned()
+                .chain(target_answer.orthos.iter().map(|o| o.remap(&mapping)))
                 .collect(),
-            pairs: self.pairs.union(&target_answer.pairs).cloned().collect(),
+            pairs: counts,
             name: self.name.clone(),
             provenance: self
                 .provenance
@@ -93,46 +186,141 @@ impl Registry {
                 .chain(target_answer.provenance.iter())
                 .cloned()
                 .collect_vec(),
+            vocabulary,
+            tokenization: self.tokenization,
+            pairing: self.pairing,
+            min_count: self.min_count.or(target_answer.min_count),
+            lineage,
+            concordance,
+            successors: HashMap::default(),
+            predecessors: HashMap::default(),
         }
+        .indexed())
     }
 
     pub(crate) fn add(&self, additional_squares: Vec<Ortho>) -> Self {
-        Registry {
-            squares: self
-                .squares
-                .union(&additional_squares.iter().cloned().collect())
-                .cloned()
-                .collect(),
-            pairs: self.pairs.clone(),
-            name: self.name.clone(),
-            provenance: self.provenance.clone(),
+        let mut registry = self.clone();
+        for square in additional_squares
+            .into_iter()
+            .map(|square| square.canonical(&self.vocabulary))
+        {
+            registry.lineage.record_square(square);
+            registry.squares.insert(square);
         }
+        registry
     }
 
-    pub(crate) fn from_book(book: &Book) -> Self {
-        Registry {
+    pub(crate) fn add_orthos(&self, additional_orthos: Vec<HyperOrtho>) -> Self {
+        let mut registry = self.clone();
+        registry.orthos.extend(additional_orthos);
+        registry
+    }
+
+    pub(crate) fn from_book(book: &Book) -> Result<Self> {
+        let mut vocabulary = Vocabulary::default();
+        let (pairs, concordance) = book.make_pairs(&mut vocabulary);
+        let name = book.calculate_name()?;
+        Ok(Registry {
             squares: HashSet::default(),
-            pairs: book.make_pairs(),
-            name: book.calculate_name(),
-            provenance: vec![book.calculate_name()],
+            orthos: HashSet::default(),
+            lineage: Lineage::of_chunk(pairs.keys()),
+            concordance,
+            pairs,
+            name: name.clone(),
+            provenance: vec![Source {
+                name,
+                chunker: book.chunker().to_string(),
+            }],
+            vocabulary,
+            tokenization: book.tokenization(),
+            pairing: book.pairing(),
+            min_count: None,
+            successors: HashMap::default(),
+            predecessors: HashMap::default(),
         }
+        .indexed())
     }
 
-    pub(crate) fn contains(&self, item: &Line) -> bool {
-        self.pairs.contains(item)
+    // either orientation of a square finds it
+    pub fn has_square(&self, a: &str, b: &str, c: &str, d: &str) -> bool {
+        self.square(a, b, c, d).is_some()
     }
 
-    fn lines_starting_with(&self, first: &String) -> Vec<&Line> {
-        self.pairs
-            .iter()
-            .filter(|l| &l.first == first)
-            .collect_vec()
+    pub(crate) fn square(&self, a: &str, b: &str, c: &str, d: &str) -> Option<Ortho> {
+        let ids = [a, b, c, d].map(|word| self.vocabulary.id(word));
+        match ids {
+            [Some(a), Some(b), Some(c), Some(d)] => {
+                Some(Ortho::new(a, b, c, d).canonical(&self.vocabulary))
+                    .filter(|square| self.squares.contains(square))
+            }
+            _ => None,
+        }
     }
 
-    pub(crate) fn contains_line_with(&self, f: &String, s: &String) -> bool {
-        self.pairs.contains(&Line {
-            first: f.clone(),
-            second: s.clone(),
+    // None if the registry does not hold the square
+    pub fn explain(&self, a: &str, b: &str, c: &str, d: &str) -> Option<Explanation<'_>> {
+        let square = self.square(a, b, c, d)?;
+        let sources = |indexes: &mut dyn Iterator<Item = u32>| {
+            indexes
+                .map(|index| &self.provenance[index as usize])
+                .collect_vec()
+        };
+        let edges = square
+            .edges()
+            .into_iter()
+            .map(|edge| (edge, sources(&mut self.lineage.pair(&edge))))
+            .collect();
+        Some(Explanation {
+            square: sources(&mut self.lineage.square(&square)),
+            edges,
         })
     }
+
+    pub fn count(&self, line: &Line) -> u64 {
+        self.pairs.get(line).copied().unwrap_or_default()
+    }
+
+    // the count of the rarest of the four edges, which is the evidence for the square as a whole
+    pub fn support(&self, square: &Ortho) -> u64 {
+        square
+            .edges()
+            .iter()
+            .map(|edge| self.count(edge))
+            .min()
+            .unwrap_or_default()
+    }
+
+    pub fn ortho_support(&self, ortho: &HyperOrtho) -> u64 {
+        ortho
+            .edges()
+            .iter()
+            .map(|edge| self.count(edge))
+            .min()
+            .unwrap_or_default()
+    }
+
+    // other's pair counts in terms of self's ids. every word of other must already be in self,
+    // as it is when self is a union that other went into
+    pub(crate) fn counts_of(&self, other: &Self) -> HashMap<Line, u64> {
+        let mapping = self.vocabulary.clone().absorb(&other.vocabulary);
+        other.remapped(&mapping).1
+    }
+
+    pub(crate) fn contains_line_with(&self, f: WordId, s: WordId, min_count: u64) -> bool {
+        self.count(&Line {
+            first: f,
+            second: s,
+        }) >= min_count
+    }
+
+    // self's squares and pairs with every id passed through mapping
+    fn remapped(&self, mapping: &[WordId]) -> (HashSet<Ortho>, HashMap<Line, u64>) {
+        let squares = self.squares.iter().map(|o| o.remap(mapping)).collect();
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|(l, count)| (l.remap(mapping), *count))
+            .collect();
+        (squares, pairs)
+    }
 }
@@ -1,258 +1,189 @@
-use aws_sdk_s3::{primitives::ByteStream, Client};
-use itertools::Itertools;
+use aws_sdk_s3::{error::DisplayErrorContext, primitives::ByteStream, Client};
 
-use crate::registry::Registry;
+use crate::error::{Error, Result};
+use crate::storage::{Storage, StoredObject};
 
-pub struct Bucket {
+pub struct S3Storage {
     client: Client,
     location: String,
 }
 
-impl Bucket {
+impl S3Storage {
     pub async fn new(endpoint: String, location: String) -> Self {
         let client =
             aws_sdk_s3::Client::new(&aws_config::from_env().endpoint_url(endpoint).load().await);
-        Bucket { client, location }
+        S3Storage { client, location }
     }
+}
 
-    pub async fn bucket_does_not_exist(&self) -> bool {
-        !self
+impl Storage for S3Storage {
+    async fn exists(&self) -> Result<bool> {
+        Ok(self
             .client
             .list_buckets()
             .send()
             .await
-            .unwrap()
+            .map_err(failure)?
             .buckets()
-            .to_vec()
             .iter()
-            .any(|b| b.name().unwrap_or_default() == self.location)
-    }
-
-    pub async fn delete_chunk(&self, registry: Registry) {
-        self.delete_from_bucket_top_level(&("singleprocessing/".to_owned() + &registry.name))
-            .await
+            .any(|b| b.name().unwrap_or_default() == self.location))
     }
 
-    pub async fn delete_answer(&self, registry: Registry) {
-        self.delete_from_bucket_top_level(&("doubleprocessing/".to_owned() + &registry.name))
-            .await
-    }
-
-    pub async fn delete_largest_answer(&self) {
-        let f = self.get_largest_file_name("answers").await;
-        self.delete_from_bucket_top_level(&("answers/".to_owned() + &f.unwrap()))
-            .await
-    }
-
-    pub async fn save_answer(&self, ans: Registry) {
-        let to_write = bincode::serialize(&ans).unwrap();
-        let write_location = ans.name();
-
-        self.save_to_bucket_top_level(&("answers/".to_string() + write_location), to_write.into())
-            .await;
-    }
-
-    pub async fn delete_from_bucket_top_level(&self, file_name: &String) {
+    async fn create(&self) -> Result<()> {
         self.client
-            .delete_object()
+            .create_bucket()
             .bucket(self.location.clone())
-            .key(file_name)
             .send()
             .await
-            .unwrap();
-    }
-
-    pub async fn dump_results(&self) {
-        dbg!(self.read_largest_chunk().await.unwrap().squares);
-    }
-
-    pub async fn write_chunk(&self, book_chunk: Registry) {
-        let to_write = bincode::serialize(&book_chunk).unwrap();
-        let write_location = book_chunk.name;
-
-        self.save_to_bucket_top_level(&("chunks/".to_string() + &write_location), to_write.into())
-            .await;
-    }
-
-    pub async fn checkout_smallest_chunk(&self) -> Option<Registry> {
-        let f = self.get_smallest_file_name("chunks").await;
-
-        if let Some(f) = f {
-            self.move_chunk(&f, "chunks", "singleprocessing/").await;
-            Some(self.read_chunk(&f, "singleprocessing/").await)
-        } else {
-            None
-        }
+            .map_err(failure)?;
+        Ok(())
     }
 
-    pub async fn checkout_largest_and_smallest_answer(&self) -> Option<(Registry, Registry)> {
-        let f = self.get_largest_and_smallest_file_name("answers").await;
-
-        if let Some((l, s)) = f {
-            self.move_chunk(&l, "answers", "doubleprocessing/").await;
-            self.move_chunk(&s, "answers", "doubleprocessing/").await;
-            Some((
-                self.read_chunk(&l, "doubleprocessing/").await,
-                self.read_chunk(&s, "doubleprocessing/").await,
-            ))
-        } else {
-            None
-        }
-    }
-
-    pub async fn read_largest_chunk(&self) -> Option<Registry> {
-        let f = self.get_largest_file_name("answers").await;
-
-        if let Some(l) = f {
-            Some(
-                self.read_chunk(&l, "answers/").await
-            )
-        } else {
-            None
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        // the delimiter keeps deeper keys (claims/chunks/... under claims) out of the listing,
+        // and the paginator follows continuation tokens past the 1000 keys of a single response
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(self.location.clone())
+            .prefix(prefix.to_owned() + "/")
+            .delimiter("/")
+            .into_paginator()
+            .send();
+
+        let mut res = vec![];
+        while let Some(page) = pages.next().await {
+            res.extend(
+                page.map_err(failure)?
+                    .contents()
+                    .iter()
+                    .map(|o| StoredObject {
+                        name: extract_filename(o),
+                        size: o.size.unwrap_or_default() as u64,
+                    }),
+            );
         }
+        Ok(res)
     }
 
-    async fn read_chunk(&self, f: &str, prefix: &str) -> Registry {
+    async fn read(&self, key: &str) -> Result<Vec<u8>> {
         let stream: ByteStream = self
             .client
             .get_object()
             .bucket(self.location.clone())
-            .key(prefix.to_string() + f)
+            .key(key)
             .send()
             .await
-            .unwrap()
+            .map_err(|e| {
+                if e.as_service_error().is_some_and(|e| e.is_no_such_key()) {
+                    Error::NotFound(key.to_string())
+                } else {
+                    failure(e)
+                }
+            })?
             .body;
 
-        let data = stream
+        Ok(stream
             .collect()
             .await
-            .expect("error reading data")
-            .into_bytes();
-        let b: Registry = bincode::deserialize(&data).unwrap();
-        b
+            .map_err(failure)?
+            .into_bytes()
+            .to_vec())
+    }
+
+    async fn write(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(self.location.clone())
+            .key(key)
+            .body(body.into())
+            .send()
+            .await
+            .map_err(failure)?;
+        Ok(())
     }
 
-    async fn get_smallest_file_name(&self, prefix: &str) -> Option<String> {
+    async fn write_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool> {
         let response = self
             .client
-            .list_objects_v2()
+            .put_object()
             .bucket(self.location.clone())
+            .key(key)
+            .if_none_match("*")
+            .body(body.into())
             .send()
             .await;
 
-        let vec = response.unwrap().contents;
-        let vec = &vec.unwrap();
-        let minimum = vec
-            .iter()
-            .filter(|o| o.key().unwrap().split('/').next().unwrap().eq(prefix))
-            .min_by(|x, y| x.size.cmp(&y.size));
-
-        if minimum.is_none() {
-            None
-        } else {
-            let thing = minimum.unwrap();
-            let key = &thing.key;
-            let key = &key.clone().unwrap();
-            let k = &key.split('/').last();
-            Some((*k).unwrap().to_string())
+        match response {
+            Ok(_) => Ok(true),
+            // 412 when the key exists, 409 when a concurrent conditional write won
+            Err(e)
+                if matches!(
+                    e.raw_response().map(|r| r.status().as_u16()),
+                    Some(409 | 412)
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(failure(e)),
         }
     }
-    
-    async fn get_largest_file_name(&self, prefix: &str) -> Option<String> {
+
+    async fn has(&self, key: &str) -> Result<bool> {
         let response = self
             .client
-            .list_objects_v2()
+            .head_object()
             .bucket(self.location.clone())
+            .key(key)
             .send()
             .await;
 
-        let vec = response.unwrap().contents;
-        let vec = &vec.unwrap();
-        let maximum = vec
-            .iter()
-            .filter(|o| o.key().unwrap().split('/').next().unwrap().eq(prefix))
-            .max_by(|x, y| x.size.cmp(&y.size));
-
-        if maximum.is_none() {
-            None
-        } else {
-            let thing = maximum.unwrap();
-            let key = &thing.key;
-            let key = &key.clone().unwrap();
-            let k = &key.split('/').last();
-            Some((*k).unwrap().to_string())
+        match response {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(false),
+            Err(e) => Err(failure(e)),
         }
     }
 
-    async fn move_chunk(&self, file_name: &String, prefix: &str, processing_prefix: &str) {
-        let mut source_bucket_and_object = "".to_string();
-        source_bucket_and_object.push_str(&self.location);
-        source_bucket_and_object.push('/');
-        source_bucket_and_object.push_str(prefix);
-        source_bucket_and_object.push('/');
-        source_bucket_and_object.push_str(file_name);
-
+    async fn delete(&self, key: &str) -> Result<()> {
         self.client
-            .copy_object()
-            .copy_source(source_bucket_and_object)
+            .delete_object()
             .bucket(self.location.clone())
-            .key(processing_prefix.to_owned() + file_name)
+            .key(key)
             .send()
             .await
-            .unwrap();
-        self.delete_from_bucket_top_level(&(prefix.to_owned() + "/" + file_name))
-            .await;
+            .map_err(failure)?;
+        Ok(())
     }
 
-    pub async fn save_to_bucket_top_level(&self, file_name: &String, body: ByteStream) {
-        self.client
-            .put_object()
-            .bucket(self.location.clone())
-            .key(file_name)
-            .body(body)
-            .send()
-            .await
-            .unwrap();
-    }
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let mut source_bucket_and_object = "".to_string();
+        source_bucket_and_object.push_str(&self.location);
+        source_bucket_and_object.push('/');
+        source_bucket_and_object.push_str(from);
 
-    pub async fn create_bucket(&self) {
         self.client
-            .create_bucket()
+            .copy_object()
+            .copy_source(source_bucket_and_object)
             .bucket(self.location.clone())
+            .key(to)
             .send()
             .await
-            .unwrap();
+            .map_err(failure)?;
+        self.delete(from).await
     }
+}
 
-    async fn get_largest_and_smallest_file_name(&self, prefix: &str) -> Option<(String, String)> {
-        let response = self
-            .client
-            .list_objects_v2()
-            .bucket(self.location.clone())
-            .send()
-            .await;
-
-        let vec = response.unwrap().contents;
-        let vec = &vec.unwrap();
-        let maximum = vec
-            .iter()
-            .filter(|o| o.key().unwrap().split('/').next().unwrap().eq(prefix))
-            .minmax_by(|x, y| x.size.cmp(&y.size));
-
-        match maximum {
-            itertools::MinMaxResult::NoElements => None,
-            itertools::MinMaxResult::OneElement(_) => None,
-            itertools::MinMaxResult::MinMax(min, max) => {
-                Some((extract_filename(min), extract_filename(max)))
-            }
-        }
-    }
+fn failure(e: impl std::error::Error) -> Error {
+    Error::storage(DisplayErrorContext(e))
 }
 
-fn extract_filename(min: &aws_sdk_s3::types::Object) -> String {
-    let thing = min;
-    let key = &thing.key;
-    let key = &key.clone().unwrap();
-    let k = &key.split('/').last();
-    (*k).unwrap().to_string()
+fn extract_filename(object: &aws_sdk_s3::types::Object) -> String {
+    object
+        .key()
+        .unwrap_or_default()
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string()
 }
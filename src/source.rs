@@ -0,0 +1,16 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// one chunk that went into a registry, and the chunker spec that cut it from its book
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Eq, Hash)]
+pub struct Source {
+    pub name: String,
+    pub chunker: String,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.chunker)
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+use std::time::Instant;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::registry::Registry;
+
+// what one single or merge pass did, saved as stats/<chunk> for a single pass and
+// stats/<source>+<target> for a merge, so every pass of a run keeps its own.
+// triples are three edges walked towards a fourth, discontinuities are candidate squares
+// neither side could have formed alone
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub name: String,
+    pub triples_examined: u64,
+    pub discontinuities: u64,
+    pub candidate_squares: u64,
+    pub new_squares: u64,
+    pub new_orthos: u64,
+    pub phases: Vec<Phase>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Phase {
+    pub name: String,
+    pub seconds: f64,
+}
+
+impl Stats {
+    // runs f and records how long it took under name
+    pub(crate) fn time<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        self.phases.push(Phase {
+            name: name.to_string(),
+            seconds: start.elapsed().as_secs_f64(),
+        });
+        res
+    }
+
+    // the squares and orthos ans has that before did not
+    pub(crate) fn count_new(&mut self, before: &Registry, ans: &Registry) {
+        self.name = ans.name().to_string();
+        self.new_squares = (ans.squares.len() - before.squares.len()) as u64;
+        self.new_orthos = (ans.orthos.len() - before.orthos.len()) as u64;
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} triples examined, {} candidate squares, {} discontinuities, {} new squares, {} new orthos ({})",
+            self.name,
+            self.triples_examined,
+            self.candidate_squares,
+            self.discontinuities,
+            self.new_squares,
+            self.new_orthos,
+            self.phases
+                .iter()
+                .map(|phase| format!("{} {:.3}s", phase.name, phase.seconds))
+                .join(", ")
+        )
+    }
+}
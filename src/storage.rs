@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use crate::{
+    error::Result, local_storage::LocalStorage, memory_storage::MemoryStorage, s3_helper::S3Storage,
+};
+
+pub struct StoredObject {
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug)]
+pub enum StorageConfig {
+    S3 { endpoint: String, location: String },
+    Local { root: PathBuf },
+    Memory(MemoryStorage),
+}
+
+// keys are slash separated, with the queue ("chunks", "answers", ...) as the first segment
+pub(crate) trait Storage {
+    async fn exists(&self) -> Result<bool>;
+    async fn create(&self) -> Result<()>;
+    // objects directly under prefix, named without the prefix
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>>;
+    async fn read(&self, key: &str) -> Result<Vec<u8>>;
+    async fn write(&self, key: &str, body: Vec<u8>) -> Result<()>;
+    // atomically creates key, returning false without writing if it is already there
+    async fn write_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool>;
+    async fn has(&self, key: &str) -> Result<bool>;
+    async fn delete(&self, key: &str) -> Result<()>;
+    async fn rename(&self, from: &str, to: &str) -> Result<()>;
+}
+
+pub(crate) enum Store {
+    S3(S3Storage),
+    Local(LocalStorage),
+    Memory(MemoryStorage),
+}
+
+impl Store {
+    pub(crate) async fn open(config: StorageConfig) -> Self {
+        match config {
+            StorageConfig::S3 { endpoint, location } => {
+                Store::S3(S3Storage::new(endpoint, location).await)
+            }
+            StorageConfig::Local { root } => Store::Local(LocalStorage::new(root)),
+            StorageConfig::Memory(storage) => Store::Memory(storage),
+        }
+    }
+}
+
+impl Storage for Store {
+    async fn exists(&self) -> Result<bool> {
+        match self {
+            Store::S3(s) => s.exists().await,
+            Store::Local(s) => s.exists().await,
+            Store::Memory(s) => s.exists().await,
+        }
+    }
+
+    async fn create(&self) -> Result<()> {
+        match self {
+            Store::S3(s) => s.create().await,
+            Store::Local(s) => s.create().await,
+            Store::Memory(s) => s.create().await,
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        match self {
+            Store::S3(s) => s.list(prefix).await,
+            Store::Local(s) => s.list(prefix).await,
+            Store::Memory(s) => s.list(prefix).await,
+        }
+    }
+
+    async fn read(&self, key: &str) -> Result<Vec<u8>> {
+        match self {
+            Store::S3(s) => s.read(key).await,
+            Store::Local(s) => s.read(key).await,
+            Store::Memory(s) => s.read(key).await,
+        }
+    }
+
+    async fn write(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        match self {
+            Store::S3(s) => s.write(key, body).await,
+            Store::Local(s) => s.write(key, body).await,
+            Store::Memory(s) => s.write(key, body).await,
+        }
+    }
+
+    async fn write_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool> {
+        match self {
+            Store::S3(s) => s.write_if_absent(key, body).await,
+            Store::Local(s) => s.write_if_absent(key, body).await,
+            Store::Memory(s) => s.write_if_absent(key, body).await,
+        }
+    }
+
+    async fn has(&self, key: &str) -> Result<bool> {
+        match self {
+            Store::S3(s) => s.has(key).await,
+            Store::Local(s) => s.has(key).await,
+            Store::Memory(s) => s.has(key).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self {
+            Store::S3(s) => s.delete(key).await,
+            Store::Local(s) => s.delete(key).await,
+            Store::Memory(s) => s.delete(key).await,
+        }
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        match self {
+            Store::S3(s) => s.rename(from, to).await,
+            Store::Local(s) => s.rename(from, to).await,
+            Store::Memory(s) => s.rename(from, to).await,
+        }
+    }
+}
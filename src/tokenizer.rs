@@ -0,0 +1,209 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+// turns the text of a chunk into sentences of words. pairs are only made within a sentence
+pub trait Tokenizer {
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str>;
+
+    fn words(&self, sentence: &str) -> Vec<String>;
+
+    fn tokenize(&self, text: &str) -> Vec<Vec<String>> {
+        self.sentences(text)
+            .into_iter()
+            .map(|sentence| self.words(sentence))
+            .collect()
+    }
+}
+
+// which tokenizer made a registry. stored with it, because words from different
+// tokenizers do not mean the same thing and must not be merged
+#[derive(ValueEnum, Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Tokenization {
+    /// Lowercased runs of letters; anything else is dropped
+    #[default]
+    Classic,
+    /// Lowercased unicode words, which keeps numbers and apostrophes
+    Unicode,
+    /// Lowercased words that keep inner apostrophes and hyphens, e.g. don't and well-known
+    Apostrophe,
+    /// Unicode words with their case left alone
+    Cased,
+}
+
+impl Tokenizer for Tokenization {
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        match self {
+            Tokenization::Classic => Classic.sentences(text),
+            Tokenization::Unicode => UnicodeWords.sentences(text),
+            Tokenization::Apostrophe => Apostrophe.sentences(text),
+            Tokenization::Cased => Cased.sentences(text),
+        }
+    }
+
+    fn words(&self, sentence: &str) -> Vec<String> {
+        match self {
+            Tokenization::Classic => Classic.words(sentence),
+            Tokenization::Unicode => UnicodeWords.words(sentence),
+            Tokenization::Apostrophe => Apostrophe.words(sentence),
+            Tokenization::Cased => Cased.words(sentence),
+        }
+    }
+}
+
+impl fmt::Display for Tokenization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+pub struct Classic;
+
+impl Tokenizer for Classic {
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.split_terminator(TERMINATORS)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.trim())
+            .collect()
+    }
+
+    fn words(&self, sentence: &str) -> Vec<String> {
+        sentence
+            .split_whitespace()
+            .flat_map(split_unspaced)
+            .map(|s| {
+                s.chars()
+                    .filter(|c| c.is_alphabetic())
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+pub struct UnicodeWords;
+
+impl Tokenizer for UnicodeWords {
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        split_sentences(text)
+    }
+
+    fn words(&self, sentence: &str) -> Vec<String> {
+        sentence
+            .unicode_words()
+            .map(|word| word.to_lowercase())
+            .collect()
+    }
+}
+
+pub struct Apostrophe;
+
+impl Tokenizer for Apostrophe {
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        split_sentences(text)
+    }
+
+    fn words(&self, sentence: &str) -> Vec<String> {
+        let joiner = |c: char| matches!(c, '\'' | '’' | '-');
+        sentence
+            .split_whitespace()
+            .flat_map(split_unspaced)
+            .map(|s| {
+                s.chars()
+                    .filter(|c| c.is_alphanumeric() || joiner(*c))
+                    .collect::<String>()
+                    .trim_matches(joiner)
+                    .replace('’', "'")
+                    .to_lowercase()
+            })
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+pub struct Cased;
+
+impl Tokenizer for Cased {
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        split_sentences(text)
+    }
+
+    fn words(&self, sentence: &str) -> Vec<String> {
+        sentence.unicode_words().map(str::to_string).collect()
+    }
+}
+
+// ascii and full-width sentence terminators, including the ideographic full stop
+pub(crate) const TERMINATORS: [char; 10] = ['.', '!', '?', ';', '\n', '。', '｡', '！', '？', '；'];
+
+const ABBREVIATIONS: [&str; 10] = [
+    "mr", "mrs", "ms", "dr", "st", "jr", "sr", "prof", "vs", "mt",
+];
+
+// splits on the terminators, except for a full stop that ends an abbreviation
+// such as Mr. or an initial, or that sits inside a number or a word such as 3.14 or e.g.
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        if !TERMINATORS.contains(&c) {
+            continue;
+        }
+        if c == '.' && !ends_sentence(&text[start..index], &text[index + 1..]) {
+            continue;
+        }
+        sentences.push(&text[start..index]);
+        start = index + c.len_utf8();
+    }
+    sentences.push(&text[start..]);
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+fn ends_sentence(before: &str, after: &str) -> bool {
+    if after.starts_with(|c: char| c.is_alphanumeric()) {
+        return false;
+    }
+    let word = before
+        .rsplit(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    let initial = word.chars().count() == 1 && word.chars().all(char::is_uppercase);
+    !initial && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+// scripts written without spaces between words, where each character is taken as a word.
+// unicode_words already does this for the tokenizers built on it
+fn is_unspaced(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // hiragana and katakana
+        | '\u{3400}'..='\u{4DBF}' // cjk extension a
+        | '\u{4E00}'..='\u{9FFF}' // cjk unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // cjk compatibility ideographs
+        | '\u{FF66}'..='\u{FF9F}' // half-width katakana
+        | '\u{20000}'..='\u{3FFFF}' // cjk extensions b and later
+    )
+}
+
+// cuts a whitespace delimited token into its spaced runs and its unspaced characters
+fn split_unspaced(token: &str) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut start = 0;
+    for (index, c) in token.char_indices() {
+        if is_unspaced(c) {
+            pieces.push(&token[start..index]);
+            pieces.push(&token[index..index + c.len_utf8()]);
+            start = index + c.len_utf8();
+        }
+    }
+    pieces.push(&token[start..]);
+    pieces.retain(|piece| !piece.is_empty());
+    pieces
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub type WordId = u32;
+
+// only the word list is serialized; the reverse lookup is rebuilt on load
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Vocabulary {
+    words: Vec<String>,
+    ids: HashMap<String, WordId>,
+}
+
+impl Vocabulary {
+    pub fn intern(&mut self, word: &str) -> WordId {
+        if let Some(id) = self.ids.get(word) {
+            return *id;
+        }
+        let id = self.words.len() as WordId;
+        self.words.push(word.to_string());
+        self.ids.insert(word.to_string(), id);
+        id
+    }
+
+    pub fn id(&self, word: &str) -> Option<WordId> {
+        self.ids.get(word).copied()
+    }
+
+    pub fn word(&self, id: WordId) -> &str {
+        &self.words[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    // interns every word of other and returns, indexed by other's ids, the ids they now have here
+    pub(crate) fn absorb(&mut self, other: &Vocabulary) -> Vec<WordId> {
+        other.words.iter().map(|word| self.intern(word)).collect()
+    }
+}
+
+impl From<Vec<String>> for Vocabulary {
+    fn from(words: Vec<String>) -> Self {
+        let ids = words
+            .iter()
+            .enumerate()
+            .map(|(id, word)| (word.clone(), id as WordId))
+            .collect();
+        Vocabulary { words, ids }
+    }
+}
+
+impl From<Vocabulary> for Vec<String> {
+    fn from(vocabulary: Vocabulary) -> Self {
+        vocabulary.words
+    }
+}
@@ -0,0 +1,926 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use itertools::Itertools;
+
+use parachute::{
+    add, answer, delete,
+    error::Error,
+    fold, get,
+    ingestion::Ingestion,
+    lease::Lease,
+    memory_storage::MemoryStorage,
+    output::{write_registry, Format, Section, View},
+    process, recover,
+    registry::Registry,
+    source::Source,
+    stats::Stats,
+    storage::StorageConfig,
+    tokenizer::{Tokenization, Tokenizer},
+};
+
+const LEASE: Duration = Duration::from_secs(3600);
+
+// a path under the temp directory that no other test uses
+fn scratch(name: &str) -> PathBuf {
+    static USED: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "parachute-{}-{}-{}",
+        name,
+        std::process::id(),
+        USED.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+// a book file in a directory of its own, removed again when dropped
+struct Book {
+    dir: PathBuf,
+    file_name: String,
+}
+
+fn write_book(text: &str) -> Book {
+    let dir = scratch("book");
+    std::fs::create_dir_all(&dir).unwrap();
+    let book = dir.join("book.txt");
+    std::fs::write(&book, text).unwrap();
+    Book {
+        file_name: book.to_string_lossy().into_owned(),
+        dir,
+    }
+}
+
+impl Drop for Book {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// folds the books in memory and loads the answer fold saves
+fn fold_to_registry(
+    file_names: &[&str],
+    ingestion: Ingestion,
+    min_count: u64,
+    extend: usize,
+    threads: usize,
+) -> Registry {
+    let path = scratch("answer");
+    fold(
+        file_names.iter().map(|name| name.to_string()).collect(),
+        ingestion,
+        View::default(),
+        min_count,
+        extend,
+        threads,
+        Some(path.clone()),
+    )
+    .unwrap();
+    let registry = bincode::deserialize(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    registry
+}
+
+fn render(registry: &Registry, view: View) -> String {
+    let mut out = vec![];
+    write_registry(registry, &view, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn fold_in_store(file_name: &str) -> MemoryStorage {
+    let storage = MemoryStorage::default();
+    add(
+        file_name.to_string(),
+        Ingestion::default(),
+        StorageConfig::Memory(storage.clone()),
+    )
+    .unwrap();
+    process(StorageConfig::Memory(storage.clone()), LEASE, 1, 0, 0).unwrap();
+    storage
+}
+
+fn assert_finds_square(file_name: &str) {
+    let storage = fold_in_store(file_name);
+    let registry = answer(StorageConfig::Memory(storage))
+        .unwrap()
+        .expect("an answer is saved");
+
+    assert!(registry.has_square("a", "b", "c", "d"));
+}
+
+#[test]
+fn finds_square_when_first_edge_arrives_last() {
+    assert_finds_square("discontinuous.txt");
+}
+
+#[test]
+fn finds_square_when_second_edge_arrives_last() {
+    assert_finds_square("discontinuous_2.txt");
+}
+
+#[test]
+fn finds_square_when_third_edge_arrives_last() {
+    assert_finds_square("discontinuous_3.txt");
+}
+
+#[test]
+fn finds_square_when_fourth_edge_arrives_last() {
+    assert_finds_square("discontinuous_4.txt");
+}
+
+#[test]
+fn finds_square_in_example() {
+    assert_finds_square("example.txt");
+}
+
+#[test]
+fn merges_every_chunk_into_one_answer() {
+    let storage = fold_in_store("discontinuous.txt");
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+
+    assert_eq!(registry.provenance.len(), 2);
+}
+
+#[test]
+fn concurrent_workers_share_one_store() {
+    let storage = MemoryStorage::default();
+    add(
+        "example.txt".to_string(),
+        Ingestion::default(),
+        StorageConfig::Memory(storage.clone()),
+    )
+    .unwrap();
+    add(
+        "discontinuous.txt".to_string(),
+        Ingestion::default(),
+        StorageConfig::Memory(storage.clone()),
+    )
+    .unwrap();
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let storage = storage.clone();
+            std::thread::spawn(move || {
+                process(StorageConfig::Memory(storage), LEASE, 1, 0, 0).unwrap()
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+    assert!(registry.has_square("a", "b", "c", "d"));
+    assert_eq!(registry.provenance.len(), 3);
+}
+
+#[test]
+fn recovers_chunks_stranded_by_a_dead_worker() {
+    let root = scratch("recover");
+    let config = || StorageConfig::Local { root: root.clone() };
+    add(
+        "discontinuous.txt".to_string(),
+        Ingestion::default(),
+        config(),
+    )
+    .unwrap();
+
+    // a worker that died after moving its chunk but before saving the answer
+    std::fs::create_dir_all(root.join("singleprocessing")).unwrap();
+    std::fs::rename(
+        root.join("chunks/discontinuous-2.txt"),
+        root.join("singleprocessing/discontinuous-2.txt"),
+    )
+    .unwrap();
+    process(config(), LEASE, 1, 0, 0).unwrap();
+
+    let registry = answer(config()).unwrap().unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert!(registry.has_square("a", "b", "c", "d"));
+    assert_eq!(registry.provenance.len(), 2);
+}
+
+#[test]
+fn leaves_the_book_it_adds_where_it_was() {
+    let root = scratch("absolute");
+    let book = write_book("a b. c d. a c. b d.");
+    add(
+        book.file_name.clone(),
+        Ingestion::default(),
+        StorageConfig::Local { root: root.clone() },
+    )
+    .unwrap();
+
+    let chunks = std::fs::read_dir(root.join("chunks")).unwrap().count();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert!(PathBuf::from(&book.file_name).is_absolute());
+    assert!(std::fs::exists(&book.file_name).unwrap());
+    assert_eq!(chunks, 1);
+}
+
+#[test]
+fn reports_missing_input_file() {
+    let result = add(
+        "no-such-book.txt".to_string(),
+        Ingestion::default(),
+        StorageConfig::Memory(MemoryStorage::default()),
+    );
+
+    assert!(matches!(result, Err(Error::Input { .. })));
+}
+
+#[test]
+fn reports_empty_store() {
+    let storage = MemoryStorage::default();
+
+    assert!(matches!(
+        get(
+            StorageConfig::Memory(storage.clone()),
+            View {
+                format: Format::Json,
+                ..Default::default()
+            }
+        ),
+        Err(Error::NoAnswer)
+    ));
+    assert!(matches!(
+        delete(StorageConfig::Memory(storage)),
+        Err(Error::NoAnswer)
+    ));
+}
+
+#[test]
+fn writes_squares_as_json_lines() {
+    let storage = fold_in_store("discontinuous.txt");
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+
+    let view = View {
+        format: Format::Jsonl,
+        ..Default::default()
+    };
+    let lines: Vec<serde_json::Value> = render(&registry, view)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(lines.contains(&serde_json::json!({"a": "a", "b": "b", "c": "c", "d": "d"})));
+}
+
+#[test]
+fn writes_pairs_as_csv() {
+    let storage = fold_in_store("discontinuous.txt");
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+
+    let view = View {
+        format: Format::Csv,
+        section: Section::Pairs,
+        ..Default::default()
+    };
+    let out = render(&registry, view);
+    assert_eq!(out.lines().next(), Some("first,second,count"));
+    assert!(out.lines().any(|line| line == "a,b,1"));
+    assert_eq!(out.lines().count(), registry.pairs.len() + 1);
+}
+
+#[test]
+fn writes_a_page_of_squares_as_tiles() {
+    let storage = fold_in_store("discontinuous.txt");
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+
+    let view = View {
+        format: Format::Tiles,
+        page: Some(1),
+        page_size: 1,
+        ..Default::default()
+    };
+    assert_eq!(render(&registry, view), "a b\nc d\n");
+}
+
+#[test]
+fn folds_files_without_a_store() {
+    let registry = fold_to_registry(
+        &["discontinuous.txt", "example.txt"],
+        Ingestion::default(),
+        1,
+        0,
+        0,
+    );
+
+    assert!(registry.has_square("a", "b", "c", "d"));
+    assert_eq!(registry.provenance.len(), 3);
+    // no sentences are kept unless asked for
+    assert!(registry
+        .pairs
+        .keys()
+        .all(|line| registry.concordance.samples(line).is_empty()));
+}
+
+#[test]
+fn records_the_chunker_in_provenance() {
+    let storage = MemoryStorage::default();
+    add(
+        "discontinuous.txt".to_string(),
+        Ingestion {
+            chunker: "sentences:2".parse().unwrap(),
+            ..Default::default()
+        },
+        StorageConfig::Memory(storage.clone()),
+    )
+    .unwrap();
+    process(StorageConfig::Memory(storage.clone()), LEASE, 1, 0, 0).unwrap();
+
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+    assert!(registry.has_square("a", "b", "c", "d"));
+    assert_eq!(registry.provenance.len(), 3);
+    assert!(registry
+        .provenance
+        .iter()
+        .all(|source| source.chunker == "sentences:2"));
+}
+
+#[test]
+fn keeps_apostrophes_hyphens_and_abbreviations() {
+    let sentences = Tokenization::Apostrophe.tokenize("Mr. Smith said don't. It's well-known!");
+
+    assert_eq!(
+        sentences,
+        vec![
+            vec!["mr", "smith", "said", "don't"],
+            vec!["it's", "well-known"]
+        ]
+    );
+}
+
+// the second book is refused when it is added, and the first is still folded on its own
+fn refuses_to_queue_alongside(ingestion: Ingestion) {
+    let storage = MemoryStorage::default();
+    add(
+        "example.txt".to_string(),
+        Ingestion::default(),
+        StorageConfig::Memory(storage.clone()),
+    )
+    .unwrap();
+    let result = add(
+        "discontinuous.txt".to_string(),
+        ingestion,
+        StorageConfig::Memory(storage.clone()),
+    );
+    assert!(matches!(result, Err(Error::Incompatible(_))));
+
+    process(StorageConfig::Memory(storage.clone()), LEASE, 1, 0, 0).unwrap();
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+    assert_eq!(registry.provenance.len(), 1);
+}
+
+#[test]
+fn refuses_to_merge_different_tokenizations() {
+    refuses_to_queue_alongside(Ingestion {
+        tokenizer: Tokenization::Cased,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn segments_unspaced_scripts_and_full_width_punctuation() {
+    let sentences = Tokenization::Classic.tokenize("我爱你。你爱我！hello\u{3000}world");
+
+    assert_eq!(
+        sentences,
+        vec![
+            vec!["我", "爱", "你"],
+            vec!["你", "爱", "我"],
+            vec!["hello", "world"]
+        ]
+    );
+}
+
+#[test]
+fn refuses_to_merge_different_pairings() {
+    refuses_to_queue_alongside(Ingestion {
+        pairing: "window:2".parse().unwrap(),
+        ..Default::default()
+    });
+}
+
+// folds each book into a store of its own, then merges the answers in one store. returns what
+// the merge did and how many answers are left queued, stranded and claimed
+fn merge_answers_folded_apart(
+    folds: [(&str, Ingestion, u64); 2],
+) -> (parachute::error::Result<()>, (usize, usize, usize)) {
+    let dir = scratch("unmergeable");
+    let config = |name: &str| StorageConfig::Local {
+        root: dir.join(name),
+    };
+    let answers = dir.join("mixed/answers");
+    std::fs::create_dir_all(&answers).unwrap();
+    for (index, (file_name, ingestion, min_count)) in folds.into_iter().enumerate() {
+        let name = index.to_string();
+        add(file_name.to_string(), ingestion, config(&name)).unwrap();
+        process(config(&name), LEASE, min_count, 0, 0).unwrap();
+        for entry in std::fs::read_dir(dir.join(&name).join("answers")).unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), answers.join(entry.file_name())).unwrap();
+        }
+    }
+
+    let result = process(config("mixed"), LEASE, 1, 0, 0);
+    let count = |queue: &str| {
+        std::fs::read_dir(dir.join("mixed").join(queue))
+            .map(|entries| entries.count())
+            .unwrap_or_default()
+    };
+    let left = (
+        count("answers"),
+        count("doubleprocessing"),
+        count("claims/answers"),
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+    (result, left)
+}
+
+#[test]
+fn puts_answers_that_cannot_be_merged_back() {
+    // stores filled by different versions could still hold answers that do not go together
+    let (result, left) = merge_answers_folded_apart([
+        (
+            "example.txt",
+            Ingestion {
+                tokenizer: Tokenization::Classic,
+                ..Default::default()
+            },
+            1,
+        ),
+        (
+            "discontinuous_2.txt",
+            Ingestion {
+                tokenizer: Tokenization::Cased,
+                ..Default::default()
+            },
+            1,
+        ),
+    ]);
+    assert!(matches!(result, Err(Error::Incompatible(_))));
+    assert_eq!(left, (2, 0, 0));
+}
+
+#[test]
+fn refuses_to_merge_answers_folded_with_different_min_counts() {
+    // a worker restarted with another --min-count on the same store
+    let (result, left) = merge_answers_folded_apart([
+        ("example.txt", Ingestion::default(), 1),
+        ("discontinuous_2.txt", Ingestion::default(), 2),
+    ]);
+    assert!(matches!(result, Err(Error::Incompatible(_))));
+    assert_eq!(left, (2, 0, 0));
+}
+
+#[test]
+fn forms_squares_only_from_edges_seen_often_enough() {
+    // the a-b-c-d square is told once in each of the first two chunks, e-f-g-h only once
+    let book = write_book("a b. c d. a c. b d. a b. c d. a c. b d. e f. g h. e g. f h.");
+    let fold_with = |min_count| {
+        fold_to_registry(
+            &[&book.file_name],
+            Ingestion {
+                chunker: "sentences:4".parse().unwrap(),
+                ..Default::default()
+            },
+            min_count,
+            0,
+            0,
+        )
+    };
+    let loose = fold_with(1);
+    let strict = fold_with(2);
+
+    assert!(loose.has_square("a", "b", "c", "d"));
+    assert!(loose.has_square("e", "f", "g", "h"));
+    assert!(strict.has_square("a", "b", "c", "d"));
+    assert!(!strict.has_square("e", "f", "g", "h"));
+
+    let view = View {
+        format: Format::Csv,
+        min_count: 2,
+        ..Default::default()
+    };
+    // the header, then the well attested square
+    assert_eq!(render(&loose, view), "a,b,c,d\na,b,c,d\n");
+}
+
+#[test]
+fn traces_each_edge_of_a_square_to_its_chunks() {
+    let storage = fold_in_store("discontinuous.txt");
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+
+    let explanation = registry.explain("a", "b", "c", "d").unwrap();
+    let names = |sources: &[&Source]| {
+        sources
+            .iter()
+            .map(|source| source.name.clone())
+            .sorted()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&explanation.square),
+        ["discontinuous-1.txt", "discontinuous-2.txt"]
+    );
+    let edges: Vec<_> = explanation
+        .edges
+        .iter()
+        .map(|(_, sources)| names(sources))
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            vec!["discontinuous-2.txt"],
+            vec!["discontinuous-1.txt"],
+            vec!["discontinuous-1.txt"],
+            vec!["discontinuous-1.txt"],
+        ]
+    );
+}
+
+#[test]
+fn keeps_example_sentences_for_each_edge() {
+    let registry = fold_to_registry(
+        &["discontinuous.txt", "example.txt"],
+        Ingestion {
+            concordance: 1,
+            ..Default::default()
+        },
+        1,
+        0,
+        0,
+    );
+
+    let view = View {
+        format: Format::Tiles,
+        page: Some(1),
+        page_size: 1,
+        sentences: true,
+        ..Default::default()
+    };
+    // one sentence per edge, from whichever book was merged in first
+    let out = render(&registry, view);
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines.len(), 2 + 4 * 2);
+    assert_eq!(lines[2], "  a b:");
+    assert!(lines[3].starts_with("    a b ("));
+    assert!(lines[3].ends_with(", chunk 1)") || lines[3].ends_with(", chunk 2)"));
+}
+
+#[test]
+fn grows_squares_into_larger_orthos_across_chunks() {
+    // the a-b-c-d square is in the first chapter and its e-f column only in the second,
+    // so the 2x3 ortho is found while merging
+    let book = write_book("chapter one\na b. c d. a c. b d.\nchapter two\nb e. d f. e f.");
+    let registry = fold_to_registry(&[&book.file_name], Ingestion::default(), 1, 1, 0);
+    assert_eq!(registry.provenance.len(), 2);
+
+    let view = View {
+        format: Format::Csv,
+        section: Section::Orthos,
+        ..Default::default()
+    };
+    let out = render(&registry, view);
+    assert!(out.starts_with("shape,words\n"));
+    assert!(out.lines().contains(&"2x3,a b e c d f"));
+
+    let view = View {
+        format: Format::Tiles,
+        section: Section::Orthos,
+        ..Default::default()
+    };
+    assert!(render(&registry, view).contains("a b e\nc d f\n"));
+
+    let view = View {
+        format: Format::Jsonl,
+        section: Section::Orthos,
+        ..Default::default()
+    };
+    let out = render(&registry, view);
+    assert!(out
+        .lines()
+        .contains(&r#"{"shape":[2,3],"words":["a","b","e","c","d","f"]}"#));
+}
+
+#[test]
+fn keeps_one_axis_order_of_each_cube() {
+    // the faces of a cube, each of which grows into it along the axis it lacks
+    let book = write_book("a b. c d. a c. b d. e f. g h. e g. f h. a e. b f. c g. d h.");
+    let registry = fold_to_registry(&[&book.file_name], Ingestion::default(), 1, 1, 0);
+    let view = View {
+        format: Format::Csv,
+        section: Section::Orthos,
+        ..Default::default()
+    };
+    let out = render(&registry, view);
+    let cubes = out
+        .lines()
+        .filter(|line| line.starts_with("2x2x2,"))
+        .collect_vec();
+    assert_eq!(cubes, ["2x2x2,a b c d e f g h"]);
+}
+
+#[test]
+fn keeps_one_orientation_of_each_square_whatever_the_merge_order() {
+    let fold_in_order =
+        |file_names: [&str; 2]| fold_to_registry(&file_names, Ingestion::default(), 1, 0, 0);
+    let forwards = fold_in_order(["discontinuous.txt", "example.txt"]);
+    let backwards = fold_in_order(["example.txt", "discontinuous.txt"]);
+
+    let view = View {
+        format: Format::Csv,
+        ..Default::default()
+    };
+    assert_eq!(render(&forwards, view.clone()), "a,b,c,d\na,b,c,d\n");
+    assert_eq!(render(&backwards, view.clone()), "a,b,c,d\na,b,c,d\n");
+
+    // c is seen before b here, so ordering by id would keep a c b d
+    for text in ["a c. a b. c d. b d.", "a b. a c. b d. c d."] {
+        let book = write_book(text);
+        let registry = fold_to_registry(&[&book.file_name], Ingestion::default(), 1, 0, 0);
+        assert_eq!(render(&registry, view.clone()), "a,b,c,d\na,b,c,d\n");
+    }
+}
+
+#[test]
+fn folds_the_same_on_one_thread_as_on_many() {
+    let fold_on = |threads| {
+        fold_to_registry(
+            &["discontinuous.txt", "discontinuous_2.txt", "example.txt"],
+            Ingestion::default(),
+            1,
+            1,
+            threads,
+        )
+    };
+    let sequential = fold_on(1);
+    let parallel = fold_on(4);
+
+    assert!(!sequential.squares.is_empty());
+    assert_eq!(sequential.squares, parallel.squares);
+    assert_eq!(sequential.orthos, parallel.orthos);
+    assert_eq!(sequential.pairs, parallel.pairs);
+}
+
+#[test]
+fn merging_finds_the_squares_folding_everything_at_once_would() {
+    let squares_with = |chunker: &str| {
+        let registry = fold_to_registry(
+            &["discontinuous.txt", "discontinuous_2.txt", "example.txt"],
+            Ingestion {
+                chunker: chunker.parse().unwrap(),
+                ..Default::default()
+            },
+            1,
+            0,
+            0,
+        );
+        let view = View {
+            format: Format::Csv,
+            ..Default::default()
+        };
+        render(&registry, view)
+    };
+    // every chapter on its own, so most squares are only found while merging
+    let merged = squares_with("heading");
+    let whole = squares_with("bytes:1000000");
+
+    assert!(merged.lines().count() > 1);
+    assert_eq!(merged, whole);
+}
+
+#[test]
+fn saves_the_stats_of_every_pass_next_to_its_answer() {
+    let root = scratch("stats");
+    let config = || StorageConfig::Local { root: root.clone() };
+    add(
+        "discontinuous.txt".to_string(),
+        Ingestion::default(),
+        config(),
+    )
+    .unwrap();
+    process(config(), LEASE, 1, 0, 0).unwrap();
+
+    let registry = answer(config()).unwrap().unwrap();
+    let stats: HashMap<String, Stats> = std::fs::read_dir(root.join("stats"))
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.file_name().into_string().unwrap(),
+                serde_json::from_slice(&std::fs::read(entry.path()).unwrap()).unwrap(),
+            )
+        })
+        .collect();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    // one per chunk and one for the merge that finds the square neither chunk holds alone,
+    // keyed by the chunks it merged
+    assert_eq!(stats.len(), 3);
+    let (key, merge) = stats.iter().find(|(key, _)| key.contains('+')).unwrap();
+    assert_eq!(merge.name, registry.name());
+    assert!(key.split('+').all(|chunk| stats.contains_key(chunk)));
+    assert_eq!(merge.discontinuities, 1);
+    assert_eq!(merge.new_squares, 1);
+    assert!(merge.candidate_squares >= merge.discontinuities);
+    assert!(merge.triples_examined >= merge.candidate_squares);
+    assert_eq!(
+        merge
+            .phases
+            .iter()
+            .map(|phase| phase.name.as_str())
+            .collect_vec(),
+        ["union", "squares", "orthos"]
+    );
+    assert!(stats
+        .iter()
+        .filter(|(other, _)| *other != key)
+        .all(|(_, stats)| stats.new_squares == 0 && stats.discontinuities == 0));
+}
+
+#[test]
+fn rebuilds_neighbour_indexes_when_a_registry_is_loaded() {
+    let storage = fold_in_store("example.txt");
+    let registry = answer(StorageConfig::Memory(storage)).unwrap().unwrap();
+
+    let loaded: Registry = bincode::deserialize(&bincode::serialize(&registry).unwrap()).unwrap();
+    assert_eq!(loaded, registry);
+    let a = loaded.vocabulary.id("a").unwrap();
+    let b = loaded.vocabulary.id("b").unwrap();
+    assert!(loaded.forward(a).contains(&b));
+}
+
+#[test]
+fn waits_for_answers_other_workers_hold_instead_of_stopping() {
+    let dir = scratch("contended");
+    let config = |name: &str| StorageConfig::Local {
+        root: dir.join(name),
+    };
+    for (name, file_name) in [("first", "example.txt"), ("second", "discontinuous_2.txt")] {
+        add(file_name.to_string(), Ingestion::default(), config(name)).unwrap();
+        process(config(name), LEASE, 1, 0, 0).unwrap();
+    }
+    // both answers in one store, one of them claimed by a live worker that lets go later
+    let answers = dir.join("shared/answers");
+    std::fs::create_dir_all(&answers).unwrap();
+    for name in ["first", "second"] {
+        for entry in std::fs::read_dir(dir.join(name).join("answers")).unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), answers.join(entry.file_name())).unwrap();
+        }
+    }
+    let held = std::fs::read_dir(&answers)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .min()
+        .unwrap();
+    let claim = dir.join("shared/claims/answers").join(&held);
+    std::fs::create_dir_all(claim.parent().unwrap()).unwrap();
+    let lease = bincode::serialize(&Lease::new("other worker")).unwrap();
+    std::fs::write(&claim, lease).unwrap();
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(500));
+        std::fs::remove_file(claim).unwrap();
+    });
+
+    process(config("shared"), LEASE, 1, 0, 0).unwrap();
+    release.join().unwrap();
+
+    let registry = answer(config("shared")).unwrap().unwrap();
+    let left = std::fs::read_dir(&answers).unwrap().count();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(left, 1);
+    assert_eq!(registry.provenance.len(), 3);
+}
+
+#[test]
+fn does_not_requeue_an_answer_already_merged_into_a_saved_one() {
+    let dir = scratch("merged");
+    let config = |name: &str| StorageConfig::Local {
+        root: dir.join(name),
+    };
+    let names_in = |path: std::path::PathBuf| {
+        std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect_vec()
+    };
+    add(
+        "discontinuous.txt".to_string(),
+        Ingestion::default(),
+        config("merged"),
+    )
+    .unwrap();
+    process(config("merged"), LEASE, 1, 0, 0).unwrap();
+    let kept = names_in(dir.join("merged/answers")).pop().unwrap();
+
+    // the answer of the other chunk alone, as a worker would have left it after saving the
+    // merged answer but dying before deleting its inputs
+    add(
+        "discontinuous.txt".to_string(),
+        Ingestion::default(),
+        config("single"),
+    )
+    .unwrap();
+    std::fs::remove_file(dir.join("single/chunks").join(&kept)).unwrap();
+    process(config("single"), LEASE, 1, 0, 0).unwrap();
+    let other = names_in(dir.join("single/answers")).pop().unwrap();
+    std::fs::create_dir_all(dir.join("merged/doubleprocessing")).unwrap();
+    std::fs::copy(
+        dir.join("single/answers").join(&other),
+        dir.join("merged/doubleprocessing").join(&other),
+    )
+    .unwrap();
+
+    recover(config("merged"), LEASE).unwrap();
+    let answers = names_in(dir.join("merged/answers"));
+    let stranded = names_in(dir.join("merged/doubleprocessing"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(answers, [kept]);
+    assert!(stranded.is_empty());
+}
+
+#[test]
+fn does_not_requeue_a_chunk_already_merged_into_a_saved_answer() {
+    let dir = scratch("chunk-merged");
+    let config = |name: &str| StorageConfig::Local {
+        root: dir.join(name),
+    };
+    let names_in = |path: std::path::PathBuf| {
+        std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                    .collect_vec()
+            })
+            .unwrap_or_default()
+    };
+    for name in ["merged", "queued"] {
+        add(
+            "discontinuous.txt".to_string(),
+            Ingestion::default(),
+            config(name),
+        )
+        .unwrap();
+    }
+    process(config("merged"), LEASE, 1, 0, 0).unwrap();
+    let kept = names_in(dir.join("merged/answers"));
+
+    // a chunk whose answer was saved, and later merged, but whose worker died before
+    // deleting it
+    let chunk = names_in(dir.join("queued/chunks")).pop().unwrap();
+    std::fs::create_dir_all(dir.join("merged/singleprocessing")).unwrap();
+    std::fs::copy(
+        dir.join("queued/chunks").join(&chunk),
+        dir.join("merged/singleprocessing").join(&chunk),
+    )
+    .unwrap();
+
+    recover(config("merged"), LEASE).unwrap();
+    let chunks = names_in(dir.join("merged/chunks"));
+    let stranded = names_in(dir.join("merged/singleprocessing"));
+    let answers = names_in(dir.join("merged/answers"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(chunks.is_empty());
+    assert!(stranded.is_empty());
+    assert_eq!(answers, kept);
+}
+
+#[test]
+fn merging_grows_the_orthos_folding_everything_at_once_would() {
+    // a 3x3 grid in the first chapter, a column past it in the second and an unrelated square
+    // in the third
+    let book = write_book(
+        "chapter one\na b c. d e f. g h i. a d g. b e h. c f i.\n\
+         chapter two\nc j. f k. i l. j k. k l.\n\
+         chapter three\nw x. y z. w y. x z.",
+    );
+    let orthos_with = |chunker: &str, extend| {
+        let registry = fold_to_registry(
+            &[&book.file_name],
+            Ingestion {
+                chunker: chunker.parse().unwrap(),
+                ..Default::default()
+            },
+            1,
+            extend,
+            0,
+        );
+        let view = View {
+            format: Format::Csv,
+            section: Section::Orthos,
+            ..Default::default()
+        };
+        render(&registry, view)
+    };
+    for extend in [1, 2] {
+        let merged = orthos_with("heading", extend);
+        let whole = orthos_with("bytes:1000000", extend);
+        assert_eq!(merged, whole);
+        // the 3x3 is two extensions away from its squares
+        assert_eq!(merged.contains("3x3,"), extend == 2);
+    }
+}